@@ -0,0 +1,130 @@
+//! Compares the throughput of `HttpConnection::send` with the byte-at-a-time
+//! body reading it used to do, against a server on the loopback interface.
+//!
+//! Run with `cargo bench -p http-course-core`.
+
+use http_course_core::{HttpConnection, HttpRequest};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+const BODY_SIZE: usize = 16 * 1024 * 1024;
+const CHUNK_SIZE: usize = 16 * 1024;
+const ROUNDS: u32 = 5;
+
+/// Answers every request on the connection with a `BODY_SIZE` body, chunked
+/// when the path is `/chunked`.
+async fn serve(mut stream: TcpStream) {
+    let body = vec![b'x'; BODY_SIZE];
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+        }
+        if request_line.contains("/chunked") {
+            writer
+                .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .await
+                .unwrap();
+            for chunk in body.chunks(CHUNK_SIZE) {
+                let size_line = format!("{:x}\r\n", chunk.len());
+                writer.write_all(size_line.as_bytes()).await.unwrap();
+                writer.write_all(chunk).await.unwrap();
+                writer.write_all(b"\r\n").await.unwrap();
+            }
+            writer.write_all(b"0\r\n\r\n").await.unwrap();
+        } else {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", BODY_SIZE);
+            writer.write_all(head.as_bytes()).await.unwrap();
+            writer.write_all(&body).await.unwrap();
+        }
+    }
+}
+
+async fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream));
+        }
+    });
+    address
+}
+
+/// Reads a Content-Length response the way the client used to: one
+/// `read_u8` call per body byte.
+async fn byte_at_a_time(address: SocketAddr) -> Duration {
+    let mut stream = BufReader::new(TcpStream::connect(address).await.unwrap());
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        stream
+            .get_mut()
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = Vec::new();
+        for _ in 0..content_length {
+            body.push(stream.read_u8().await.unwrap());
+        }
+        assert_eq!(body.len(), BODY_SIZE);
+    }
+    start.elapsed()
+}
+
+/// Reads the responses with `HttpConnection::send`.
+async fn bulk(address: SocketAddr, path: &str) -> Duration {
+    let url = Url::parse(&format!("http://{}{}", address, path)).unwrap();
+    let mut connection = HttpConnection::connect(&url).await.unwrap();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let response = connection
+            .send(HttpRequest::get(url.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.body.len(), BODY_SIZE);
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let megabytes = (BODY_SIZE as f64 * ROUNDS as f64) / (1024.0 * 1024.0);
+    println!(
+        "{:<32} {:>8.1} MiB/s ({:?} for {} x {} MiB)",
+        name,
+        megabytes / elapsed.as_secs_f64(),
+        elapsed,
+        ROUNDS,
+        BODY_SIZE / (1024 * 1024)
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let address = start_server().await;
+    report("read_u8, Content-Length", byte_at_a_time(address).await);
+    report("send, Content-Length", bulk(address, "/").await);
+    report("send, chunked", bulk(address, "/chunked").await);
+}
@@ -0,0 +1,372 @@
+//! Response bodies read incrementally from the connection.
+
+use async_compression::tokio::bufread::GzipDecoder;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::chunked::{MAX_CHUNK_SIZE_LINE, MAX_TRAILER_LINE, parse_chunk_size_line};
+use crate::response::parse_header_line;
+use crate::{ChunkExtension, HeaderMap, HttpConnection, HttpError, Result};
+
+/// How the end of a response body is found.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Framing {
+    Empty,
+    ContentLength(u64),
+    Chunked,
+    /// The body ends when the server closes the connection.
+    UntilClose,
+}
+
+#[derive(Debug)]
+enum State {
+    Length { expected: u64, remaining: u64 },
+    ChunkSize { line: Vec<u8> },
+    ChunkData { size: usize, remaining: usize },
+    ChunkEnd { read: usize },
+    Trailers { line: Vec<u8> },
+    UntilClose,
+    Done,
+}
+
+/// The body as sent on the wire, with the transfer framing removed but the
+/// content coding still applied.
+#[derive(Debug)]
+pub(crate) struct RawBody<'a, S> {
+    connection: &'a mut HttpConnection<S>,
+    state: State,
+    chunk: usize,
+    chunk_extensions: Vec<ChunkExtension>,
+    trailers: HeaderMap,
+}
+
+impl<'a, S: AsyncRead + AsyncWrite + Unpin> RawBody<'a, S> {
+    pub(crate) fn new(connection: &'a mut HttpConnection<S>, framing: Framing) -> Self {
+        let state = match framing {
+            Framing::Empty => State::Done,
+            Framing::ContentLength(length) => State::Length {
+                expected: length,
+                remaining: length,
+            },
+            Framing::Chunked => State::ChunkSize { line: Vec::new() },
+            Framing::UntilClose => State::UntilClose,
+        };
+        let mut body = Self {
+            connection,
+            state,
+            chunk: 0,
+            chunk_extensions: Vec::new(),
+            trailers: HeaderMap::new(),
+        };
+        if matches!(body.state, State::Done) {
+            body.finish();
+        }
+        body
+    }
+
+    fn finish(&mut self) {
+        self.state = State::Done;
+        self.connection.idle = true;
+    }
+
+    /// Reads the framing of the body until some data or the end of the
+    /// body is reached.
+    fn poll_framing(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            let stream = &mut self.connection.stream;
+            match &mut self.state {
+                State::Length { remaining: 0, .. } => self.finish(),
+                State::ChunkSize { line } => {
+                    let line = ready!(poll_crlf_line(stream, cx, line, MAX_CHUNK_SIZE_LINE))
+                        .map_err(|error| match error {
+                            LineError::TooLong => HttpError::BadChunkSize(format!(
+                                "chunk size line longer than {} bytes",
+                                MAX_CHUNK_SIZE_LINE
+                            )),
+                            LineError::Http(error) => error,
+                        })?;
+                    let (size, extensions) = parse_chunk_size_line(&line, self.chunk)?;
+                    self.chunk += 1;
+                    self.chunk_extensions.extend(extensions);
+                    self.state = if size == 0 {
+                        State::Trailers { line: Vec::new() }
+                    } else {
+                        State::ChunkData {
+                            size,
+                            remaining: size,
+                        }
+                    };
+                }
+                State::ChunkData { remaining: 0, .. } => self.state = State::ChunkEnd { read: 0 },
+                State::ChunkEnd { read } => {
+                    let buffer = ready!(Pin::new(&mut *stream).poll_fill_buf(cx))?;
+                    let Some(&byte) = buffer.first() else {
+                        return Poll::Ready(Err(HttpError::InvalidChunkedEncoding(
+                            "unexpected end of stream after chunk data".to_string(),
+                        )));
+                    };
+                    if byte != b"\r\n"[*read] {
+                        let missing = if *read == 0 {
+                            "carriage return"
+                        } else {
+                            "new line"
+                        };
+                        return Poll::Ready(Err(HttpError::InvalidChunkedEncoding(format!(
+                            "missing {} after chunk data",
+                            missing
+                        ))));
+                    }
+                    Pin::new(&mut *stream).consume(1);
+                    *read += 1;
+                    if *read == 2 {
+                        self.state = State::ChunkSize { line: Vec::new() };
+                    }
+                }
+                State::Trailers { line } => {
+                    let line = ready!(poll_crlf_line(stream, cx, line, MAX_TRAILER_LINE)).map_err(
+                        |error| match error {
+                            LineError::TooLong => HttpError::InvalidHeader(format!(
+                                "trailer line longer than {} bytes",
+                                MAX_TRAILER_LINE
+                            )),
+                            LineError::Http(error) => error,
+                        },
+                    )?;
+                    if line.is_empty() {
+                        self.finish();
+                    } else {
+                        let (key, value) = parse_header_line(&String::from_utf8_lossy(&line))?;
+                        self.trailers.append(key, value);
+                    }
+                }
+                State::UntilClose => {
+                    let buffer = ready!(Pin::new(&mut *stream).poll_fill_buf(cx))?;
+                    if buffer.is_empty() {
+                        self.finish();
+                    } else {
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+                State::Length { .. } | State::ChunkData { .. } | State::Done => {
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncBufRead for RawBody<'_, S> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        ready!(this.poll_framing(cx)).map_err(io::Error::other)?;
+        let (available, truncated) = match this.state {
+            State::Length {
+                expected,
+                remaining,
+            } => (
+                usize::try_from(remaining).unwrap_or(usize::MAX),
+                HttpError::TruncatedBody {
+                    expected: usize::try_from(expected).unwrap_or(usize::MAX),
+                    received: usize::try_from(expected - remaining).unwrap_or(usize::MAX),
+                },
+            ),
+            State::ChunkData { size, remaining } => (
+                remaining,
+                HttpError::TruncatedBody {
+                    expected: size,
+                    received: size - remaining,
+                },
+            ),
+            State::UntilClose => (usize::MAX, HttpError::ConnectionClosed),
+            _ => return Poll::Ready(Ok(&[])),
+        };
+        let buffer = ready!(Pin::new(&mut this.connection.stream).poll_fill_buf(cx))
+            .map_err(|error| io::Error::other(HttpError::from(error)))?;
+        if buffer.is_empty() {
+            return Poll::Ready(Err(io::Error::other(truncated)));
+        }
+        let length = buffer.len().min(available);
+        Poll::Ready(Ok(&buffer[..length]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amount: usize) {
+        let this = self.get_mut();
+        Pin::new(&mut this.connection.stream).consume(amount);
+        match &mut this.state {
+            State::Length { remaining, .. } => *remaining -= amount as u64,
+            State::ChunkData { remaining, .. } => *remaining -= amount,
+            _ => {}
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for RawBody<'_, S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let length = available.len().min(buf.remaining());
+        buf.put_slice(&available[..length]);
+        self.consume(length);
+        Poll::Ready(Ok(()))
+    }
+}
+
+enum LineError {
+    TooLong,
+    Http(HttpError),
+}
+
+impl From<io::Error> for LineError {
+    fn from(error: io::Error) -> Self {
+        LineError::Http(error.into())
+    }
+}
+
+/// Reads a line terminated by CRLF into `line` and returns it without the
+/// CRLF; `line` keeps the partial line while the stream is pending.
+fn poll_crlf_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    cx: &mut Context<'_>,
+    line: &mut Vec<u8>,
+    limit: usize,
+) -> Poll<std::result::Result<Vec<u8>, LineError>> {
+    loop {
+        let buffer = ready!(Pin::new(&mut *reader).poll_fill_buf(cx))?;
+        if buffer.is_empty() {
+            return Poll::Ready(Err(LineError::Http(HttpError::InvalidChunkedEncoding(
+                "unexpected end of stream in chunked body".to_string(),
+            ))));
+        }
+        let (found, used) = match buffer.iter().position(|&byte| byte == b'\n') {
+            Some(position) => (true, position + 1),
+            None => (false, buffer.len()),
+        };
+        line.extend_from_slice(&buffer[..used]);
+        Pin::new(&mut *reader).consume(used);
+        if line.len() > limit {
+            return Poll::Ready(Err(LineError::TooLong));
+        }
+        if found {
+            break;
+        }
+    }
+    let mut line = mem::take(line);
+    if !line.ends_with(b"\r\n") {
+        return Poll::Ready(Err(LineError::Http(HttpError::InvalidChunkedEncoding(
+            "missing carriage return at end of line".to_string(),
+        ))));
+    }
+    line.truncate(line.len() - 2);
+    Poll::Ready(Ok(line))
+}
+
+#[derive(Debug)]
+enum Decoder<'a, S> {
+    Identity(RawBody<'a, S>),
+    /// A gzip decoder, and whether the body turned out to have some bytes
+    /// to decode.
+    Gzip(GzipDecoder<RawBody<'a, S>>, bool),
+}
+
+/// A response body that is read as it arrives, with its transfer framing
+/// removed and a gzip content coding decoded.
+///
+/// The connection can send the next request once the body has been read to
+/// the end; dropping the body before that leaves the connection unusable.
+#[derive(Debug)]
+pub struct ResponseBody<'a, S> {
+    decoder: Decoder<'a, S>,
+}
+
+impl<'a, S: AsyncRead + AsyncWrite + Unpin> ResponseBody<'a, S> {
+    pub(crate) fn new(raw: RawBody<'a, S>, gzip: bool) -> Self {
+        let decoder = if gzip {
+            Decoder::Gzip(GzipDecoder::new(raw), false)
+        } else {
+            Decoder::Identity(raw)
+        };
+        Self { decoder }
+    }
+
+    fn raw(&self) -> &RawBody<'a, S> {
+        match &self.decoder {
+            Decoder::Identity(raw) => raw,
+            Decoder::Gzip(decoder, _) => decoder.get_ref(),
+        }
+    }
+
+    /// Tells whether the whole body has been read from the connection.
+    pub fn is_done(&self) -> bool {
+        matches!(self.raw().state, State::Done)
+    }
+
+    /// Extensions of the chunks read so far.
+    pub fn chunk_extensions(&self) -> &[ChunkExtension] {
+        &self.raw().chunk_extensions
+    }
+
+    /// Trailer fields, available once the body has been read to the end.
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.raw().trailers
+    }
+
+    pub(crate) fn into_parts(self) -> (Vec<ChunkExtension>, HeaderMap) {
+        let raw = match self.decoder {
+            Decoder::Identity(raw) => raw,
+            Decoder::Gzip(decoder, _) => decoder.into_inner(),
+        };
+        (raw.chunk_extensions, raw.trailers)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for ResponseBody<'_, S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().decoder {
+            Decoder::Identity(raw) => Pin::new(raw).poll_read(cx, buf),
+            Decoder::Gzip(decoder, started) => {
+                // an empty body, like `Content-Length: 0`, is an empty
+                // content rather than a truncated gzip stream
+                if !*started {
+                    let raw = decoder.get_mut();
+                    if ready!(Pin::new(&mut *raw).poll_fill_buf(cx))?.is_empty() {
+                        return Poll::Ready(Ok(()));
+                    }
+                    *started = true;
+                }
+                let filled = buf.filled().len();
+                ready!(Pin::new(&mut *decoder).poll_read(cx, buf)).map_err(|error| {
+                    // errors of the connection are already wrapped, anything
+                    // else comes from the gzip decoder
+                    if error.get_ref().is_some_and(|inner| inner.is::<HttpError>()) {
+                        error
+                    } else {
+                        io::Error::other(HttpError::Decompression(error))
+                    }
+                })?;
+                if buf.filled().len() == filled && buf.remaining() > 0 {
+                    // the gzip stream is over: skip what is left of the body
+                    // so that the connection is ready for the next request
+                    let raw = decoder.get_mut();
+                    loop {
+                        let length = ready!(Pin::new(&mut *raw).poll_fill_buf(cx))?.len();
+                        if length == 0 {
+                            break;
+                        }
+                        Pin::new(&mut *raw).consume(length);
+                    }
+                }
+                Poll::Ready(Ok(()))
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Validation of the framing of `Transfer-Encoding: chunked` bodies.
+
+use crate::{HttpError, Result};
+
+/// Longest chunk size line that is accepted, extensions and CRLF included.
+pub(crate) const MAX_CHUNK_SIZE_LINE: usize = 1024;
+/// Longest trailer field line that is accepted, CRLF included.
+pub(crate) const MAX_TRAILER_LINE: usize = 8192;
+
+/// A `;name=value` extension sent after the size of a chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkExtension {
+    /// Index of the chunk carrying the extension; the last (empty) chunk is
+    /// included in the count.
+    pub chunk: usize,
+    pub name: String,
+    /// The value with the quotes of a quoted-string removed, if any.
+    pub value: Option<String>,
+}
+
+/// Parses the size line of the `chunk`-th chunk, without CRLF, into the
+/// chunk size and its extensions.
+pub(crate) fn parse_chunk_size_line(
+    line: &[u8],
+    chunk: usize,
+) -> Result<(usize, Vec<ChunkExtension>)> {
+    let (size, extensions) = match line.iter().position(|&byte| byte == b';') {
+        Some(position) => {
+            // whitespace is allowed between the size and the extensions
+            let size = line[..position].trim_ascii_end();
+            let extensions = parse_chunk_extensions(&line[position..], chunk).ok_or_else(|| {
+                HttpError::InvalidChunkedEncoding(format!(
+                    "invalid chunk extension: {:?}",
+                    String::from_utf8_lossy(line)
+                ))
+            })?;
+            (size, extensions)
+        }
+        None => (line, Vec::new()),
+    };
+    Ok((parse_chunk_size(size)?, extensions))
+}
+
+/// Parses the hexadecimal size of a chunk.
+///
+/// Only hexadecimal digits are accepted (no sign, no whitespace) and sizes
+/// that do not fit in a `usize` are rejected.
+pub(crate) fn parse_chunk_size(size: &[u8]) -> Result<usize> {
+    let bad_chunk_size = || HttpError::BadChunkSize(String::from_utf8_lossy(size).into_owned());
+    if size.is_empty() {
+        return Err(bad_chunk_size());
+    }
+    size.iter().try_fold(0usize, |total, &byte| {
+        let digit = char::from(byte).to_digit(16).ok_or_else(bad_chunk_size)?;
+        total
+            .checked_mul(16)
+            .and_then(|total| total.checked_add(digit as usize))
+            .ok_or_else(bad_chunk_size)
+    })
+}
+
+/// Parses `*( BWS ";" BWS name [ BWS "=" BWS value ] )` where the value is
+/// a token or a quoted-string.
+fn parse_chunk_extensions(mut input: &[u8], chunk: usize) -> Option<Vec<ChunkExtension>> {
+    let mut extensions = Vec::new();
+    loop {
+        input = input.trim_ascii_start();
+        if input.is_empty() {
+            return Some(extensions);
+        }
+        input = input.strip_prefix(b";")?.trim_ascii_start();
+        let (name, rest) = split_token(input)?;
+        input = rest.trim_ascii_start();
+        let value = match input.strip_prefix(b"=") {
+            Some(rest) => {
+                let rest = rest.trim_ascii_start();
+                let (value, rest) = if rest.starts_with(b"\"") {
+                    split_quoted_string(rest)?
+                } else {
+                    let (value, rest) = split_token(rest)?;
+                    (value.to_string(), rest)
+                };
+                input = rest;
+                Some(value)
+            }
+            None => None,
+        };
+        extensions.push(ChunkExtension {
+            chunk,
+            name: name.to_string(),
+            value,
+        });
+    }
+}
+
+/// Splits the leading token (RFC 9110 `tchar`s) off `input`.
+fn split_token(input: &[u8]) -> Option<(&str, &[u8])> {
+    let is_tchar = |byte: &u8| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(byte);
+    let end = input
+        .iter()
+        .position(|byte| !is_tchar(byte))
+        .unwrap_or(input.len());
+    if end == 0 {
+        return None;
+    }
+    let token = std::str::from_utf8(&input[..end]).ok()?;
+    Some((token, &input[end..]))
+}
+
+/// Splits the leading quoted-string off `input`, unescaping its content.
+fn split_quoted_string(input: &[u8]) -> Option<(String, &[u8])> {
+    let mut value = Vec::new();
+    let mut index = 1;
+    loop {
+        match *input.get(index)? {
+            b'"' => break,
+            b'\\' => {
+                value.push(*input.get(index + 1)?);
+                index += 2;
+            }
+            byte => {
+                value.push(byte);
+                index += 1;
+            }
+        }
+    }
+    let value = String::from_utf8(value).ok()?;
+    Some((value, &input[index + 1..]))
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::connector::with_timeout;
+use crate::{
+    Connector, CookieJar, HttpConnection, HttpError, HttpRequest, HttpResponse, Origin, Proxy,
+    RedirectPolicy, Resolver, Result, RetryPolicy, TimeoutKind, TlsConfig,
+};
+
+const DEFAULT_MAX_CONNECTIONS_PER_HOST: usize = 8;
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// An HTTP client that keeps a pool of connections per origin.
+///
+/// Connections are kept open after a response when the server allows it,
+/// and reused by the next request to the same origin. Redirects are
+/// followed according to a [`RedirectPolicy`]. Cloning the client is
+/// cheap and the clones share the pool.
+///
+/// ```no_run
+/// # use http_course_core::{HttpClient, HttpRequest};
+/// # use url::Url;
+/// # async fn run() -> http_course_core::Result<()> {
+/// let client = HttpClient::new().max_connections_per_host(4);
+/// let url = Url::parse("https://gioyingtec.com").unwrap();
+/// let response = client.send(HttpRequest::get(url.clone())).await?;
+/// // reuses the connection opened by the first request
+/// let response = client.send(HttpRequest::get(url)).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    pool: Arc<Mutex<HashMap<Origin, HostPool>>>,
+    max_connections_per_host: usize,
+    idle_timeout: Duration,
+    redirect_policy: RedirectPolicy,
+    cookie_jar: Option<Arc<CookieJar>>,
+    connector: Connector,
+    request_timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+#[derive(Debug)]
+struct HostPool {
+    /// Idle connections, the most recently used last.
+    idle: Vec<IdleConnection>,
+    /// One permit per connection in use.
+    permits: Arc<Semaphore>,
+}
+
+#[derive(Debug)]
+struct IdleConnection {
+    connection: HttpConnection,
+    expires_at: Instant,
+}
+
+/// A connection taken from the pool, counted against the limit of its host
+/// until it is dropped or returned.
+struct PooledConnection {
+    origin: Origin,
+    connection: HttpConnection,
+    /// The connection was idle in the pool, so it may have been closed by
+    /// the server in the meantime.
+    reused: bool,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpClient {
+    pub fn new() -> Self {
+        Self {
+            pool: Arc::new(Mutex::new(HashMap::new())),
+            max_connections_per_host: DEFAULT_MAX_CONNECTIONS_PER_HOST,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            redirect_policy: RedirectPolicy::default(),
+            cookie_jar: None,
+            connector: Connector::new(),
+            request_timeout: None,
+            retry_policy: None,
+        }
+    }
+
+    /// Caps the number of connections open at the same time to one origin;
+    /// requests beyond the cap wait for a connection to be released.
+    pub fn max_connections_per_host(mut self, max: usize) -> Self {
+        self.max_connections_per_host = max.max(1);
+        self
+    }
+
+    /// Closes connections that stayed idle for longer than `timeout`.
+    ///
+    /// A shorter `Keep-Alive: timeout=` announced by the server wins.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Sets which redirects are followed.
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = policy;
+        self
+    }
+
+    /// Sends failed requests again according to `policy`; by default a
+    /// request is sent once.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Opens the connections with `connector`.
+    pub fn connector(mut self, connector: Connector) -> Self {
+        self.connector = connector;
+        self
+    }
+
+    /// See [`Connector::connect_timeout`].
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connector = self.connector.connect_timeout(timeout);
+        self
+    }
+
+    /// See [`Connector::tls_handshake_timeout`].
+    pub fn tls_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.connector = self.connector.tls_handshake_timeout(timeout);
+        self
+    }
+
+    /// See [`Connector::read_timeout`].
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.connector = self.connector.read_timeout(timeout);
+        self
+    }
+
+    /// See [`Connector::resolver`].
+    pub fn resolver(mut self, resolver: impl Resolver + 'static) -> Self {
+        self.connector = self.connector.resolver(resolver);
+        self
+    }
+
+    /// See [`Connector::happy_eyeballs_delay`].
+    pub fn happy_eyeballs_delay(mut self, delay: Duration) -> Self {
+        self.connector = self.connector.happy_eyeballs_delay(delay);
+        self
+    }
+
+    /// See [`Connector::proxy`].
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.connector = self.connector.proxy(proxy);
+        self
+    }
+
+    /// See [`Connector::tls`].
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.connector = self.connector.tls(config);
+        self
+    }
+
+    /// Limits the time spent in [`HttpClient::send`], redirects included.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Stores the cookies set by the responses in `jar`, and sends them back
+    /// with the requests they match. The jar can be shared with other
+    /// clients.
+    pub fn cookie_jar(mut self, jar: Arc<CookieJar>) -> Self {
+        self.cookie_jar = Some(jar);
+        self
+    }
+
+    /// The cookie jar of the client, if any.
+    pub fn cookies(&self) -> Option<&Arc<CookieJar>> {
+        self.cookie_jar.as_ref()
+    }
+
+    /// Sends the request and reads the whole response, following redirects
+    /// as allowed by the [`RedirectPolicy`] and retrying as allowed by the
+    /// [`RetryPolicy`], if any.
+    pub async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        with_timeout(
+            self.request_timeout,
+            TimeoutKind::Request,
+            self.send_with_retries(request),
+        )
+        .await?
+    }
+
+    async fn send_with_retries(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let Some(policy) = &self.retry_policy else {
+            return self.send_following_redirects(request).await;
+        };
+        let started = Instant::now();
+        let max_attempts = policy.max_attempts_for(request.method);
+        let mut attempt = 1;
+        loop {
+            if attempt == max_attempts {
+                return self.send_following_redirects(request).await;
+            }
+            let outcome = self.send_following_redirects(request.clone()).await;
+            if !policy.should_retry(&outcome) {
+                return outcome;
+            }
+            let delay = policy.delay(attempt, outcome.as_ref().ok());
+            if started.elapsed() + delay >= policy.max_elapsed_time() {
+                return outcome;
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_following_redirects(&self, mut request: HttpRequest) -> Result<HttpResponse> {
+        let mut redirects = Vec::new();
+        loop {
+            // the request is only needed again when a redirect is followed
+            let sent = (self.redirect_policy.limit() > 0).then(|| request.clone());
+            let mut response = self.send_once(request).await?;
+            let next = match &sent {
+                Some(sent) => self.redirect_policy.follow(sent, &response)?,
+                None => None,
+            };
+            let Some(next) = next else {
+                response.redirects = redirects;
+                return Ok(response);
+            };
+            if redirects.len() == self.redirect_policy.limit() {
+                return Err(HttpError::TooManyRedirects(redirects.len()));
+            }
+            redirects.push(next.uri.clone());
+            request = next;
+        }
+    }
+
+    /// Sends the request on a pooled connection to its origin and reads the
+    /// whole response.
+    ///
+    /// When a pooled connection turns out to be closed by the server, an
+    /// idempotent request is sent again on a new connection, while the
+    /// others fail with [`HttpError::NotRetried`].
+    async fn send_once(&self, mut request: HttpRequest) -> Result<HttpResponse> {
+        let origin = Origin::from_url(&request.uri)?;
+        let url = request.uri.clone();
+        if let Some(jar) = &self.cookie_jar
+            && let Some(cookies) = jar.cookie_header(&url)
+        {
+            // after the cookies set by the caller, if any
+            let cookies = match request.headers.get("Cookie") {
+                Some(own) => format!("{}; {}", own, cookies),
+                None => cookies,
+            };
+            request.headers.insert("Cookie", cookies);
+        }
+        let mut pooled = self.checkout(origin.clone()).await?;
+        let method = request.method;
+        let replay = (pooled.reused && method.is_idempotent()).then(|| request.clone());
+        let response = match pooled.connection.send(request).await {
+            Err(HttpError::StaleConnection) => {
+                let Some(request) = replay else {
+                    return Err(HttpError::NotRetried(method));
+                };
+                // keep the permit, but not the dead connection
+                pooled.connection = self.connector.connect(&origin).await?;
+                pooled.connection.send(request).await?
+            }
+            response => response?,
+        };
+        self.checkin(pooled);
+        if let Some(jar) = &self.cookie_jar {
+            jar.store(&url, &response.headers);
+        }
+        Ok(response)
+    }
+
+    /// Number of idle connections kept for `origin`.
+    pub fn idle_connections(&self, origin: &Origin) -> usize {
+        let pool = self.pool.lock().unwrap();
+        pool.get(origin).map_or(0, |host| host.idle.len())
+    }
+
+    /// Closes all the idle connections.
+    pub fn close_idle_connections(&self) {
+        let mut pool = self.pool.lock().unwrap();
+        for host in pool.values_mut() {
+            host.idle.clear();
+        }
+    }
+
+    /// Takes an idle connection to `origin` from the pool, or opens a new one.
+    async fn checkout(&self, origin: Origin) -> Result<PooledConnection> {
+        let permits = {
+            let mut pool = self.pool.lock().unwrap();
+            let host = pool.entry(origin.clone()).or_insert_with(|| HostPool {
+                idle: Vec::new(),
+                permits: Arc::new(Semaphore::new(self.max_connections_per_host)),
+            });
+            host.permits.clone()
+        };
+        let permit = permits
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+
+        let idle = {
+            let mut pool = self.pool.lock().unwrap();
+            let host = pool.get_mut(&origin).expect("inserted above");
+            let now = Instant::now();
+            // expired or dead connections are dropped on the way
+            let mut found = None;
+            while let Some(mut idle) = host.idle.pop() {
+                if idle.expires_at > now && idle.connection.is_reusable() {
+                    found = Some(idle.connection);
+                    break;
+                }
+            }
+            found
+        };
+        let reused = idle.is_some();
+        let connection = match idle {
+            Some(connection) => connection,
+            None => self.connector.connect(&origin).await?,
+        };
+        Ok(PooledConnection {
+            origin,
+            connection,
+            reused,
+            _permit: permit,
+        })
+    }
+
+    /// Returns a connection to the pool if it can send another request.
+    fn checkin(&self, mut pooled: PooledConnection) {
+        if !pooled.connection.is_reusable() {
+            return;
+        }
+        let idle_timeout = match pooled.connection.keep_alive_timeout() {
+            Some(timeout) => timeout.min(self.idle_timeout),
+            None => self.idle_timeout,
+        };
+        let mut pool = self.pool.lock().unwrap();
+        if let Some(host) = pool.get_mut(&pooled.origin) {
+            host.idle.push(IdleConnection {
+                connection: pooled.connection,
+                expires_at: Instant::now() + idle_timeout,
+            });
+        }
+    }
+}
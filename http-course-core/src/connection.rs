@@ -0,0 +1,351 @@
+use std::io::ErrorKind;
+#[cfg(unix)]
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Waker};
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use url::Url;
+
+use crate::body::{Framing, RawBody};
+use crate::proxy::ProxyServer;
+use crate::response::{parse_header_line, parse_status_line};
+use crate::transport::TimeoutStream;
+use crate::{
+    Connector, HeaderMap, HttpError, HttpMethod, HttpRequest, HttpResponse, MaybeTlsStream, Origin,
+    ResponseBody, Result, TlsInfo,
+};
+
+/// Size of the buffer between the stream and the response parser.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+/// Largest body buffer allocated before the body is received.
+const MAX_BODY_PREALLOCATION: usize = 16 * 1024 * 1024;
+/// Longest status or header line that is accepted, CRLF included.
+const MAX_HEAD_LINE: usize = 8192;
+
+/// An HTTP/1.1 connection over any byte stream.
+///
+/// [`HttpConnection::connect`] opens a TCP, TLS or Unix socket stream
+/// depending on the scheme of the url, while
+/// [`HttpConnection::from_stream`] wraps a stream that was opened by the
+/// caller.
+#[derive(Debug)]
+pub struct HttpConnection<S = MaybeTlsStream> {
+    pub(crate) stream: BufReader<TimeoutStream<S>>,
+    /// No response is being read, so a new request can be sent.
+    pub(crate) idle: bool,
+    /// The server will close the connection after the current response.
+    pub(crate) must_close: bool,
+    /// How long the server keeps the connection open while idle, from the
+    /// `Keep-Alive` header of the last response.
+    keep_alive_timeout: Option<Duration>,
+    /// Number of responses received, to tell a reused connection apart.
+    responses: usize,
+    /// What was negotiated during the TLS handshake, for TLS connections
+    /// opened by a [`Connector`].
+    pub(crate) tls_info: Option<TlsInfo>,
+    /// The forward proxy at the other end of the stream, to which requests
+    /// are sent with the whole url as target.
+    pub(crate) forward_proxy: Option<ProxyServer>,
+}
+
+impl HttpConnection<MaybeTlsStream> {
+    pub async fn connect(url: &Url) -> Result<Self> {
+        Self::connect_origin(&Origin::from_url(url)?).await
+    }
+
+    /// Opens a connection to `origin`: plain TCP for `http`, TLS for
+    /// `https` and a Unix socket for `http+unix`.
+    pub async fn connect_origin(origin: &Origin) -> Result<Self> {
+        Connector::new().connect(origin).await
+    }
+
+    /// Opens a connection to the server listening on the Unix socket at
+    /// `path`, like the Docker Engine at `/var/run/docker.sock`. The
+    /// requests sent on it can have any `http://` url: only its path and
+    /// query are used.
+    #[cfg(unix)]
+    pub async fn connect_unix(path: impl AsRef<Path>) -> Result<Self> {
+        let stream = UnixStream::connect(path).await?;
+        Ok(Self::from_stream(MaybeTlsStream::Unix(stream)))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> HttpConnection<S> {
+    pub fn from_stream(stream: S) -> Self {
+        Self {
+            stream: BufReader::with_capacity(READ_BUFFER_SIZE, TimeoutStream::new(stream)),
+            idle: true,
+            must_close: false,
+            keep_alive_timeout: None,
+            responses: 0,
+            tls_info: None,
+            forward_proxy: None,
+        }
+    }
+
+    /// Tells whether another request can be sent: the last response was
+    /// read to the end, neither side asked to close the connection and the
+    /// server has not closed it in the meantime.
+    pub fn is_reusable(&mut self) -> bool {
+        if !self.idle || self.must_close {
+            return false;
+        }
+        // nothing is expected from the server between two responses: data
+        // means a broken exchange, end of stream means the server closed it
+        if !self.stream.buffer().is_empty() {
+            return false;
+        }
+        let mut cx = Context::from_waker(Waker::noop());
+        let open = Pin::new(&mut self.stream)
+            .poll_fill_buf(&mut cx)
+            .is_pending();
+        // the probe arms the read timer, which would otherwise expire while
+        // the connection is idle and fail the next check
+        self.stream.get_mut().stop_timer();
+        open
+    }
+
+    /// Fails the reads that wait longer than `timeout` for data from the
+    /// server with [`TimeoutKind::Read`](crate::TimeoutKind::Read). The time
+    /// spent idle between two requests does not count.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.stream.get_mut().set_timeout(timeout);
+    }
+
+    /// What was negotiated during the TLS handshake, if the connection was
+    /// opened with TLS by a [`Connector`].
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
+    }
+
+    /// The idle timeout announced by the server with `Keep-Alive: timeout=`.
+    pub fn keep_alive_timeout(&self) -> Option<Duration> {
+        self.keep_alive_timeout
+    }
+
+    /// Sends the request and reads the whole response, body included.
+    pub async fn send(&mut self, request: HttpRequest) -> Result<HttpResponse> {
+        let response = self.send_streaming(request).await?;
+        let mut body = response.body;
+        let mut bytes = Vec::new();
+        if let Ok(Some(length)) = response.headers.content_length() {
+            bytes.reserve((length as usize).min(MAX_BODY_PREALLOCATION));
+        }
+        body.read_to_end(&mut bytes).await?;
+        let (chunk_extensions, trailers) = body.into_parts();
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response.headers,
+            body: bytes,
+            chunk_extensions,
+            trailers,
+            redirects: Vec::new(),
+            tls_info: self.tls_info.clone(),
+        })
+    }
+
+    /// Sends the request and returns as soon as the response headers are
+    /// read, leaving the body to be read from [`StreamingResponse::body`].
+    pub async fn send_streaming(
+        &mut self,
+        request: HttpRequest,
+    ) -> Result<StreamingResponse<'_, S>> {
+        if !self.idle {
+            return Err(HttpError::ConnectionNotIdle);
+        }
+        if self.must_close {
+            return Err(HttpError::ConnectionClosed);
+        }
+        // until the body is read to the end, the connection cannot be reused
+        self.idle = false;
+
+        // write the request and wait for the first response; if the server
+        // already closed a kept-alive connection, report it as stale so the
+        // request can be sent again on a new connection
+        let reused = self.responses > 0;
+        let mut head = self.write_request(&request).await;
+        if let Err(error) = &head
+            && reused
+            && is_closed_by_server(error)
+        {
+            self.must_close = true;
+            head = Err(HttpError::StaleConnection);
+        }
+        let (mut version, mut status, mut headers) = head?;
+
+        // skip interim responses like 100 Continue or 103 Early Hints
+        while (100..200).contains(&status) && status != 101 {
+            (version, status, headers) = self.read_head().await?;
+        }
+        self.responses += 1;
+
+        // persistence of the connection: HTTP/1.1 keeps it open unless either
+        // side says otherwise, HTTP/1.0 closes it unless asked to keep it
+        let keep_alive = if version == "HTTP/1.0" {
+            headers.contains_token("Connection", "keep-alive")
+        } else {
+            !headers.contains_token("Connection", "close")
+        };
+        if !keep_alive || request.headers.contains_token("Connection", "close") {
+            self.must_close = true;
+        }
+        self.keep_alive_timeout = parse_keep_alive_timeout(&headers);
+
+        // body, following the message body length rules of RFC 9112
+        let framing = if request.method == HttpMethod::Head
+            || (100..200).contains(&status)
+            || status == 204
+            || status == 304
+        {
+            // no body for HEAD requests and for 1xx, 204 and 304 responses
+            if status == 101 {
+                // the server switched to another protocol
+                self.must_close = true;
+            }
+            Framing::Empty
+        } else if headers.contains("Transfer-Encoding") {
+            // Transfer-Encoding overrides Content-Length, but a response
+            // with both may be an attempt at request smuggling
+            if headers.contains("Content-Length") {
+                self.must_close = true;
+            }
+            if headers.is_chunked() {
+                Framing::Chunked
+            } else {
+                self.must_close = true;
+                Framing::UntilClose
+            }
+        } else if let Some(body_length) = headers.content_length()? {
+            Framing::ContentLength(body_length)
+        } else {
+            // the body ends when the server closes the connection
+            self.must_close = true;
+            Framing::UntilClose
+        };
+        // handle gzip, unless there is no body to decode, as in the response
+        // to a HEAD request which has the headers of the response to a GET
+        let gzip = !matches!(framing, Framing::Empty)
+            && matches!(
+                headers.content_encoding().as_slice(),
+                [coding] if coding.eq_ignore_ascii_case("gzip")
+            );
+        let body = ResponseBody::new(RawBody::new(self, framing), gzip);
+
+        Ok(StreamingResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> HttpConnection<S> {
+    /// Writes the request and reads the head of the first response.
+    async fn write_request(&mut self, request: &HttpRequest) -> Result<(String, u16, HeaderMap)> {
+        let bytes = match &self.forward_proxy {
+            Some(proxy) => request.to_proxy_bytes("HTTP/1.1", proxy.authorization().as_deref()),
+            None => request.to_bytes("HTTP/1.1"),
+        };
+        self.stream.write_all(&bytes).await?;
+        self.stream.flush().await?;
+        self.read_head().await
+    }
+
+    /// Reads a status line and the header lines that follow it.
+    async fn read_head(&mut self) -> Result<(String, u16, HeaderMap)> {
+        // first line
+        let Some(line) = self.read_head_line().await? else {
+            return Err(HttpError::ConnectionClosed);
+        };
+        let (version, status) = parse_status_line(&line)?;
+        let version = version.to_string();
+
+        // headers
+        let mut headers = HeaderMap::new();
+        loop {
+            let Some(line) = self.read_head_line().await? else {
+                return Err(HttpError::TruncatedHead);
+            };
+            if !line.trim().is_empty() {
+                let (key, value) = parse_header_line(&line)?;
+                headers.append(key, value);
+            } else {
+                break;
+            }
+        }
+        Ok((version, status, headers))
+    }
+
+    /// Reads a line of the head, or `None` at the end of the stream.
+    ///
+    /// Header values may hold any byte but are rarely anything else than
+    /// ASCII, so the line is decoded lossily instead of failing the response.
+    async fn read_head_line(&mut self) -> Result<Option<String>> {
+        let mut line = Vec::new();
+        loop {
+            let buffer = self.stream.fill_buf().await?;
+            if buffer.is_empty() {
+                break;
+            }
+            let (found, used) = match buffer.iter().position(|&byte| byte == b'\n') {
+                Some(position) => (true, position + 1),
+                None => (false, buffer.len()),
+            };
+            line.extend_from_slice(&buffer[..used]);
+            self.stream.consume(used);
+            if line.len() > MAX_HEAD_LINE {
+                return Err(HttpError::InvalidHeader(format!(
+                    "line of the head longer than {} bytes",
+                    MAX_HEAD_LINE
+                )));
+            }
+            if found {
+                break;
+            }
+        }
+        if line.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
+}
+
+/// Tells whether `error` means the server closed or reset the connection.
+fn is_closed_by_server(error: &HttpError) -> bool {
+    match error {
+        HttpError::ConnectionClosed => true,
+        HttpError::Io(error) => matches!(
+            error.kind(),
+            ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+/// Parses the `timeout` parameter of a `Keep-Alive: timeout=5, max=100` header.
+fn parse_keep_alive_timeout(headers: &HeaderMap) -> Option<Duration> {
+    headers.get_list("Keep-Alive").find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("timeout") {
+            return None;
+        }
+        let seconds = value.trim().trim_matches('"').parse().ok()?;
+        Some(Duration::from_secs(seconds))
+    })
+}
+
+/// A response whose body has not been read yet.
+#[derive(Debug)]
+pub struct StreamingResponse<'a, S = MaybeTlsStream> {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: ResponseBody<'a, S>,
+}
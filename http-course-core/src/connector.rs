@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::{CertificateError, ClientConfig};
+
+use crate::proxy::{
+    ProxyProtocol, ProxyServer, SocksTarget, http_tunnel, is_socks_unreachable, socks5_tunnel,
+};
+use crate::resolver::no_addresses;
+use crate::{
+    HttpConnection, HttpError, MaybeTlsStream, Origin, Proxy, Resolver, Result, SystemResolver,
+    TimeoutKind, TlsConfig, TlsInfo,
+};
+
+const DEFAULT_HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+/// Opens the connections of an [`HttpClient`](crate::HttpClient): plain TCP
+/// for `http`, TLS for `https` and a Unix socket for `http+unix`.
+///
+/// No timeout is set by default, the connections are direct, the hosts are
+/// resolved by the [`SystemResolver`] and the servers are verified with
+/// the default [`TlsConfig`].
+///
+/// When a host has several addresses, they are tried the Happy Eyeballs
+/// way (RFC 8305): alternating IPv6 and IPv4, each attempt starting when
+/// the previous one failed or after a delay, and the first connection
+/// wins. An unreachable address family costs a delay, not a timeout.
+#[derive(Debug, Clone)]
+pub struct Connector {
+    connect_timeout: Option<Duration>,
+    tls_handshake_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    tls_config: Arc<ClientConfig>,
+    /// The configurations of the origins with their own client identity.
+    origin_tls_configs: HashMap<Origin, Arc<ClientConfig>>,
+    resolver: Arc<dyn Resolver>,
+    happy_eyeballs_delay: Duration,
+    proxy: Proxy,
+}
+
+impl Default for Connector {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            tls_handshake_timeout: None,
+            read_timeout: None,
+            tls_config: TlsConfig::new().client_config(),
+            origin_tls_configs: HashMap::new(),
+            resolver: Arc::new(SystemResolver),
+            happy_eyeballs_delay: DEFAULT_HAPPY_EYEBALLS_DELAY,
+            proxy: Proxy::new(),
+        }
+    }
+}
+
+impl Connector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verifies the servers according to `config`.
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.tls_config = config.client_config();
+        self.origin_tls_configs = config
+            .custom_origins()
+            .into_iter()
+            .map(|origin| (origin.clone(), config.client_config_for(origin)))
+            .collect();
+        self
+    }
+
+    /// Resolves the hosts with `resolver`.
+    pub fn resolver(mut self, resolver: impl Resolver + 'static) -> Self {
+        self.resolver = Arc::new(resolver);
+        self
+    }
+
+    /// Goes through the proxies of `proxy`.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// How long an attempt to connect to an address is left alone before
+    /// the next address is tried too; 250 ms by default.
+    pub fn happy_eyeballs_delay(mut self, delay: Duration) -> Self {
+        self.happy_eyeballs_delay = delay;
+        self
+    }
+
+    /// Limits the time spent resolving the host and opening the TCP
+    /// connection, through the proxy tunnel if any.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits the time spent in the TLS handshake.
+    pub fn tls_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.tls_handshake_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits the time a connection waits for the next bytes from the
+    /// server, see [`HttpConnection::set_read_timeout`].
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Opens a connection to `origin`.
+    pub async fn connect(&self, origin: &Origin) -> Result<HttpConnection> {
+        #[cfg(unix)]
+        if let Some(path) = origin.unix_socket_path() {
+            let stream = with_timeout(
+                self.connect_timeout,
+                TimeoutKind::Connect,
+                UnixStream::connect(path),
+            )
+            .await??;
+            let mut connection = HttpConnection::from_stream(MaybeTlsStream::Unix(stream));
+            connection.set_read_timeout(self.read_timeout);
+            return Ok(connection);
+        }
+        let tls = match origin.scheme() {
+            "http" => false,
+            "https" => true,
+            _ => return Err(HttpError::InvalidUrl(origin.to_string())),
+        };
+
+        // connect to the server, or to the proxy
+        let proxy = self.proxy.server_for(origin);
+        let tcp_stream = with_timeout(self.connect_timeout, TimeoutKind::Connect, async {
+            match proxy {
+                Some(proxy) => self.connect_through(proxy, origin, tls).await,
+                None => self.connect_tcp(origin).await,
+            }
+        })
+        .await??;
+        let mut tls_info = None;
+        let stream = if tls {
+            let config = self
+                .origin_tls_configs
+                .get(origin)
+                .unwrap_or(&self.tls_config);
+            let tls_connector = TlsConnector::from(config.clone());
+            let server_name = origin.server_name()?;
+            let tls_stream = with_timeout(
+                self.tls_handshake_timeout,
+                TimeoutKind::TlsHandshake,
+                tls_connector.connect(server_name, tcp_stream),
+            )
+            .await?
+            .map_err(|error| handshake_error(error, origin))?;
+            tls_info = Some(TlsInfo::from_connection(tls_stream.get_ref().1));
+            MaybeTlsStream::Tls(Box::new(tls_stream))
+        } else {
+            MaybeTlsStream::Plain(tcp_stream)
+        };
+        let mut connection = HttpConnection::from_stream(stream);
+        connection.tls_info = tls_info;
+        if !tls {
+            connection.forward_proxy = proxy
+                .filter(|proxy| proxy.protocol == ProxyProtocol::Http)
+                .cloned();
+        }
+        connection.set_read_timeout(self.read_timeout);
+        Ok(connection)
+    }
+
+    /// Resolves the host of `origin` and connects to the first address
+    /// that accepts.
+    async fn connect_tcp(&self, origin: &Origin) -> Result<TcpStream> {
+        let addresses = self.resolve(origin).await?;
+        Ok(race(interleave_families(addresses), self.happy_eyeballs_delay).await?)
+    }
+
+    /// Connects to `proxy`, then through it to `origin`: a forward HTTP
+    /// proxy is left to connect by itself, the others open a tunnel.
+    async fn connect_through(
+        &self,
+        proxy: &ProxyServer,
+        origin: &Origin,
+        tls: bool,
+    ) -> Result<TcpStream> {
+        let stream = self.connect_tcp(&proxy.origin).await?;
+        match proxy.protocol {
+            ProxyProtocol::Http if tls => http_tunnel(stream, origin, proxy).await,
+            ProxyProtocol::Http => Ok(stream),
+            ProxyProtocol::Socks5 { remote_dns } => {
+                let target = match origin.ip_address() {
+                    Some(address) => SocksTarget::Address(address),
+                    None if remote_dns => SocksTarget::Domain(origin.bare_host()),
+                    None => return self.socks5_any_address(stream, origin, proxy).await,
+                };
+                socks5_tunnel(stream, target, origin.port(), proxy).await
+            }
+        }
+    }
+
+    /// Asks the SOCKS5 proxy for the addresses of the host of `origin` one
+    /// after the other, until it reaches one. A failed request ends the
+    /// session, so each new address gets a new connection to the proxy.
+    async fn socks5_any_address(
+        &self,
+        mut stream: TcpStream,
+        origin: &Origin,
+        proxy: &ProxyServer,
+    ) -> Result<TcpStream> {
+        let mut addresses = self.resolve(origin).await?.into_iter().peekable();
+        while let Some(address) = addresses.next() {
+            let target = SocksTarget::Address(address.ip());
+            match socks5_tunnel(stream, target, origin.port(), proxy).await {
+                Err(error) if is_socks_unreachable(&error) && addresses.peek().is_some() => {
+                    stream = self.connect_tcp(&proxy.origin).await?;
+                }
+                result => return result,
+            }
+        }
+        Err(no_addresses(origin.host()).into())
+    }
+
+    /// The addresses of the host of `origin`, never empty.
+    async fn resolve(&self, origin: &Origin) -> Result<Vec<SocketAddr>> {
+        let addresses = match origin.ip_address() {
+            Some(address) => vec![SocketAddr::new(address, origin.port())],
+            None => {
+                self.resolver
+                    .resolve(origin.bare_host(), origin.port())
+                    .await?
+            }
+        };
+        if addresses.is_empty() {
+            return Err(no_addresses(origin.host()).into());
+        }
+        Ok(addresses)
+    }
+}
+
+/// Alternates IPv6 and IPv4 addresses, starting with the family of the
+/// first one, which the resolver prefers.
+fn interleave_families(addresses: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let prefer_ipv6 = addresses.first().is_some_and(SocketAddr::is_ipv6);
+    let (preferred, other): (Vec<_>, Vec<_>) = addresses
+        .into_iter()
+        .partition(|address| address.is_ipv6() == prefer_ipv6);
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut interleaved = Vec::new();
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return interleaved,
+            (first, second) => interleaved.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Connects to `addresses` in order, starting the next attempt when one
+/// fails or after `delay`, and returns the first connection; the other
+/// attempts are cancelled.
+async fn race(addresses: Vec<SocketAddr>, delay: Duration) -> io::Result<TcpStream> {
+    let mut pending = addresses.into_iter();
+    // dropping the set aborts the attempts still running
+    let mut attempts = JoinSet::new();
+    let mut last_error = None;
+    loop {
+        match pending.next() {
+            Some(address) => {
+                attempts.spawn(TcpStream::connect(address));
+            }
+            None if attempts.is_empty() => {
+                return Err(last_error.unwrap_or_else(|| io::ErrorKind::NotConnected.into()));
+            }
+            None => {}
+        }
+        tokio::select! {
+            Some(outcome) = attempts.join_next() => match outcome {
+                Ok(Ok(stream)) => return Ok(stream),
+                Ok(Err(error)) => last_error = Some(error),
+                Err(error) => last_error = Some(io::Error::other(error)),
+            },
+            _ = tokio::time::sleep(delay), if !pending.as_slice().is_empty() => {}
+        }
+    }
+}
+
+/// Tells a server key that does not match the pins of the origin apart
+/// from the other handshake errors.
+fn handshake_error(error: std::io::Error, origin: &Origin) -> HttpError {
+    let pin_mismatch = error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<tokio_rustls::rustls::Error>())
+        .is_some_and(|inner| {
+            matches!(
+                inner,
+                tokio_rustls::rustls::Error::InvalidCertificate(
+                    CertificateError::ApplicationVerificationFailure
+                )
+            )
+        });
+    if pin_mismatch {
+        HttpError::PinMismatch(origin.to_string())
+    } else {
+        HttpError::Io(error)
+    }
+}
+
+/// Runs `future`, failing with a timeout error of the given kind if it does
+/// not complete within `timeout`.
+pub(crate) async fn with_timeout<F: Future>(
+    timeout: Option<Duration>,
+    kind: TimeoutKind,
+    future: F,
+) -> Result<F::Output> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .map_err(|_| HttpError::Timeout(kind)),
+        None => Ok(future.await),
+    }
+}
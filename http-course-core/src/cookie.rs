@@ -0,0 +1,420 @@
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::{Host, Url};
+
+use crate::HeaderMap;
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// A cookie stored in a [`CookieJar`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The host that set the cookie, or the `Domain` attribute.
+    pub domain: String,
+    /// The cookie is only sent to `domain` itself, not to its subdomains,
+    /// because it had no `Domain` attribute.
+    pub host_only: bool,
+    pub path: String,
+    /// When the cookie expires; `None` for a session cookie.
+    pub expires: Option<SystemTime>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` header value received from `url`, following
+    /// RFC 6265, section 5.2. Returns `None` for a cookie that must be
+    /// ignored.
+    pub fn parse(set_cookie: &str, url: &Url) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut attributes = set_cookie.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        };
+        let mut max_age = None;
+        for attribute in attributes {
+            let (name, value) = match attribute.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "expires" => {
+                    if let Some(expires) = parse_cookie_date(value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "max-age" => {
+                    if let Ok(seconds) = value.parse::<i64>() {
+                        max_age = Some(seconds);
+                    }
+                }
+                "domain" => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    if !domain.is_empty() {
+                        cookie.domain = domain;
+                        cookie.host_only = false;
+                    }
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => Some(SameSite::Strict),
+                        "lax" => Some(SameSite::Lax),
+                        "none" => Some(SameSite::None),
+                        _ => None,
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Max-Age wins over Expires, and a non positive one expires the
+        // cookie right away
+        if let Some(seconds) = max_age {
+            cookie.expires = Some(match u64::try_from(seconds) {
+                Ok(seconds) if seconds > 0 => SystemTime::now() + Duration::from_secs(seconds),
+                _ => UNIX_EPOCH,
+            });
+        }
+        // a server can only set cookies for its own domain and the domains
+        // above it, but not for a top-level domain shared by unrelated sites;
+        // without a public suffix list, single labels are the ones refused
+        if !cookie.host_only {
+            if !domain_matches(url, &cookie.domain) {
+                return None;
+            }
+            if !cookie.domain.contains('.') {
+                // Domain=localhost from localhost is the host itself
+                if cookie.domain != host {
+                    return None;
+                }
+                cookie.host_only = true;
+            }
+        }
+        Some(cookie)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= SystemTime::now())
+    }
+
+    /// Tells whether the cookie must be sent with a request to `url`.
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let domain_ok = if self.host_only {
+            host.eq_ignore_ascii_case(&self.domain)
+        } else {
+            domain_matches(url, &self.domain)
+        };
+        domain_ok
+            && path_matches(url.path(), &self.path)
+            && (!self.secure || url.scheme() == "https")
+            && !self.is_expired()
+    }
+}
+
+/// A store of cookies, shared by all the requests of an
+/// [`HttpClient`](crate::HttpClient).
+///
+/// Cookies received with `Set-Cookie` are sent back with the `Cookie`
+/// header to the matching urls. `HttpOnly` and `SameSite` are kept, but
+/// they only matter to browsers.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    /// The cookies, in creation order.
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the cookies of the `Set-Cookie` headers of a response to `url`.
+    pub fn store(&self, url: &Url, headers: &HeaderMap) {
+        for set_cookie in headers.get_all("Set-Cookie") {
+            if let Some(cookie) = Cookie::parse(set_cookie, url) {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    /// Adds a cookie, replacing the one with the same name, domain and path.
+    /// An expired cookie removes the one it replaces.
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies.lock().unwrap();
+        let existing = cookies.iter().position(|old| {
+            old.name == cookie.name && old.domain == cookie.domain && old.path == cookie.path
+        });
+        match (existing, cookie.is_expired()) {
+            (Some(index), true) => {
+                cookies.remove(index);
+            }
+            // the replaced cookie keeps its place in the creation order
+            (Some(index), false) => cookies[index] = cookie,
+            (None, true) => {}
+            (None, false) => cookies.push(cookie),
+        }
+    }
+
+    /// The value of the `Cookie` header for a request to `url`, or `None`
+    /// when no cookie matches.
+    pub fn cookie_header(&self, url: &Url) -> Option<String> {
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|cookie| !cookie.is_expired());
+        let mut matching: Vec<&Cookie> = cookies.iter().filter(|c| c.matches(url)).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        // longer paths first, then older cookies first (RFC 6265, 5.4)
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<String> = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// A copy of the cookies that have not expired.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let cookies = self.cookies.lock().unwrap();
+        cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired())
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    /// Writes the cookies to `path` in the Netscape format used by curl and
+    /// wget. Session cookies are written with an expiry of 0.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = String::from("# Netscape HTTP Cookie File\n\n");
+        for cookie in self.cookies() {
+            let prefix = if cookie.http_only { "#HttpOnly_" } else { "" };
+            let domain = if cookie.host_only {
+                cookie.domain.clone()
+            } else {
+                format!(".{}", cookie.domain)
+            };
+            let expires = cookie
+                .expires
+                .and_then(|expires| expires.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |expires| expires.as_secs());
+            let _ = writeln!(
+                file,
+                "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                prefix,
+                domain,
+                bool_field(!cookie.host_only),
+                cookie.path,
+                bool_field(cookie.secure),
+                expires,
+                cookie.name,
+                cookie.value
+            );
+        }
+        std::fs::write(path, file)
+    }
+
+    /// Reads a cookie jar from a file in the Netscape format, skipping the
+    /// lines that cannot be parsed and the expired cookies.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let jar = Self::new();
+        for line in std::fs::read_to_string(path)?.lines() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (line, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [
+                domain,
+                include_subdomains,
+                path,
+                secure,
+                expires,
+                name,
+                value,
+            ] = fields[..]
+            else {
+                continue;
+            };
+            let Ok(expires) = expires.parse::<u64>() else {
+                continue;
+            };
+            jar.insert(Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+                host_only: !include_subdomains.eq_ignore_ascii_case("TRUE"),
+                path: path.to_string(),
+                expires: (expires != 0).then(|| UNIX_EPOCH + Duration::from_secs(expires)),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                http_only,
+                same_site: None,
+            });
+        }
+        Ok(jar)
+    }
+}
+
+fn bool_field(value: bool) -> &'static str {
+    if value { "TRUE" } else { "FALSE" }
+}
+
+/// The directory of the url path, used when a cookie has no `Path`
+/// attribute (RFC 6265, 5.1.4).
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// Tells whether the host of `url` is `domain` or one of its subdomains;
+/// IP addresses only match themselves (RFC 6265, 5.1.3).
+fn domain_matches(url: &Url, domain: &str) -> bool {
+    match url.host() {
+        Some(Host::Domain(host)) => {
+            let host = host.to_ascii_lowercase();
+            host == domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        }
+        Some(_) => url.host_str().is_some_and(|host| host == domain),
+        None => false,
+    }
+}
+
+/// Tells whether `request_path` is `cookie_path` or below it
+/// (RFC 6265, 5.1.4).
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Parses the `Expires` attribute with the lenient algorithm of RFC 6265,
+/// section 5.1.1, which accepts the forms found in the wild, like
+/// `Thu, 01-Jan-1970 00:00:00 GMT` or `Sunday, 06-Nov-94 08:49:37 GMT`.
+fn parse_cookie_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let is_delimiter =
+        |byte: u8| matches!(byte, b'\t' | 0x20..=0x2f | 0x3b..=0x40 | 0x5b..=0x60 | 0x7b..=0x7e);
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in value.split(|c: char| c.is_ascii() && is_delimiter(c as u8)) {
+        if token.is_empty() {
+            continue;
+        }
+        if time.is_none()
+            && let Some(parsed) = parse_time(token)
+        {
+            time = Some(parsed);
+        } else if day.is_none()
+            && let Some((parsed, _)) = leading_digits(token, 1, 2)
+        {
+            day = Some(parsed);
+        } else if month.is_none()
+            && let Some(index) = MONTHS.iter().position(|name| {
+                token
+                    .get(..3)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+            })
+        {
+            month = Some(index as u64 + 1);
+        } else if year.is_none()
+            && let Some((parsed, _)) = leading_digits(token, 2, 4)
+        {
+            year = Some(parsed);
+        }
+    }
+    let ((hour, minute, second), day, month, year) = (time?, day?, month?, year?);
+    // two-digit years
+    let year = match year {
+        70..=99 => year + 1900,
+        0..=69 => year + 2000,
+        _ => year,
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if year < 1601 || !(1..=days_in_month).contains(&day) || hour > 23 || minute > 59 || second > 59
+    {
+        return None;
+    }
+
+    // days since 1970-01-01, from the civil calendar
+    let (year, month) = (year as i64, month as i64);
+    let shifted = if month <= 2 { year - 1 } else { year };
+    let era = shifted.div_euclid(400);
+    let year_of_era = shifted - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    let seconds = days * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    // any date before 1970 is just as expired
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).unwrap_or(0)))
+}
+
+/// Parses the `hh:mm:ss` time of a cookie date, each field with one or two
+/// digits.
+fn parse_time(token: &str) -> Option<(u64, u64, u64)> {
+    let (hour, rest) = leading_digits(token, 1, 2)?;
+    let (minute, rest) = leading_digits(rest.strip_prefix(':')?, 1, 2)?;
+    let (second, _) = leading_digits(rest.strip_prefix(':')?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+/// Parses the `min` to `max` digits that start `token`, which must not be
+/// followed by another digit, and returns the rest of the token.
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<(u64, &str)> {
+    let length = token.bytes().take_while(u8::is_ascii_digit).count();
+    if !(min..=max).contains(&length) {
+        return None;
+    }
+    Some((token[..length].parse().ok()?, &token[length..]))
+}
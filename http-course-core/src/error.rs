@@ -0,0 +1,165 @@
+use std::fmt;
+
+use crate::HttpMethod;
+
+/// Everything that can go wrong while talking to an HTTP server.
+#[derive(Debug)]
+pub enum HttpError {
+    /// The url cannot be used to open a connection.
+    InvalidUrl(String),
+    /// The status line is missing the protocol or a numeric status code.
+    MalformedStatusLine(String),
+    /// A header line is not in the `name: value` form, or its value is unusable.
+    InvalidHeader(String),
+    /// The server closed the connection before the blank line that ends the
+    /// headers of the response.
+    TruncatedHead,
+    /// A chunk size line of a chunked body is not a hexadecimal number, does
+    /// not fit in a `usize` or is too long.
+    BadChunkSize(String),
+    /// A chunked body is missing a CRLF or ends in the middle of its framing.
+    InvalidChunkedEncoding(String),
+    /// The server closed the connection before sending the whole body.
+    TruncatedBody {
+        expected: usize,
+        received: usize,
+    },
+    /// The server closed the connection, so it cannot send another request.
+    ConnectionClosed,
+    /// The previous response on the connection was not read to the end, so
+    /// the connection cannot send another request.
+    ConnectionNotIdle,
+    /// A kept-alive connection was closed by the server before any byte of
+    /// the response arrived, most likely while it was idle.
+    StaleConnection,
+    /// The request was sent on a stale connection, but it is not idempotent,
+    /// so it was not sent again: the server may have processed it.
+    NotRetried(HttpMethod),
+    /// The request was redirected more times than allowed by the
+    /// [`RedirectPolicy`](crate::RedirectPolicy).
+    TooManyRedirects(usize),
+    /// A phase of the request took longer than its timeout.
+    Timeout(TimeoutKind),
+    /// A certificate or a key cannot be loaded.
+    Certificate(String),
+    /// The server of the origin presented a key that does not match the pins
+    /// set with [`TlsConfig::pin_sha256`](crate::TlsConfig::pin_sha256).
+    PinMismatch(String),
+    /// The proxy answered the `CONNECT` request with this status instead of
+    /// opening a tunnel; 407 means the credentials are missing or wrong.
+    ProxyTunnel(u16),
+    /// The SOCKS5 proxy failed to open a tunnel, for the given reason.
+    SocksProxy(String),
+    /// The body could not be decoded with its `Content-Encoding`.
+    Decompression(std::io::Error),
+    Io(std::io::Error),
+}
+
+/// The phase of a request that timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// Opening the TCP connection, name resolution and proxy tunnel
+    /// included.
+    Connect,
+    /// The TLS handshake.
+    TlsHandshake,
+    /// Waiting for data from the server.
+    Read,
+    /// The whole request, from connecting to reading the end of the body.
+    Request,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutKind::Connect => write!(f, "connect"),
+            TimeoutKind::TlsHandshake => write!(f, "TLS handshake"),
+            TimeoutKind::Read => write!(f, "read"),
+            TimeoutKind::Request => write!(f, "request"),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, HttpError>;
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::InvalidUrl(url) => write!(f, "invalid url: {}", url),
+            HttpError::MalformedStatusLine(line) => {
+                write!(f, "malformed status line: {:?}", line)
+            }
+            HttpError::InvalidHeader(line) => write!(f, "invalid header: {:?}", line),
+            HttpError::TruncatedHead => {
+                write!(f, "the connection closed before the end of the headers")
+            }
+            HttpError::BadChunkSize(line) => write!(f, "bad chunk size: {:?}", line),
+            HttpError::InvalidChunkedEncoding(reason) => {
+                write!(f, "invalid chunked encoding: {}", reason)
+            }
+            HttpError::TruncatedBody { expected, received } => write!(
+                f,
+                "truncated body: expected {} bytes, received {}",
+                expected, received
+            ),
+            HttpError::ConnectionClosed => write!(f, "the server closed the connection"),
+            HttpError::ConnectionNotIdle => write!(
+                f,
+                "the previous response on this connection was not read to the end"
+            ),
+            HttpError::StaleConnection => write!(
+                f,
+                "the server closed the kept-alive connection before responding"
+            ),
+            HttpError::NotRetried(method) => write!(
+                f,
+                "{} not retried on a new connection, as it is not idempotent",
+                method
+            ),
+            HttpError::TooManyRedirects(count) => {
+                write!(f, "too many redirects: stopped after {}", count)
+            }
+            HttpError::Timeout(kind) => write!(f, "{} timed out", kind),
+            HttpError::Certificate(reason) => write!(f, "invalid certificate: {}", reason),
+            HttpError::PinMismatch(origin) => {
+                write!(f, "the key of {} does not match its pins", origin)
+            }
+            HttpError::ProxyTunnel(status) => {
+                write!(f, "the proxy refused the tunnel with status {}", status)
+            }
+            HttpError::SocksProxy(reason) => write!(f, "SOCKS5 proxy error: {}", reason),
+            HttpError::Decompression(error) => write!(f, "decompression failed: {}", error),
+            HttpError::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpError::Decompression(error) | HttpError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for HttpError {
+    fn from(error: std::io::Error) -> Self {
+        // errors of a streamed body travel through `AsyncRead` wrapped in an
+        // `std::io::Error`
+        if error.get_ref().is_some_and(|inner| inner.is::<HttpError>()) {
+            let inner = error.into_inner().expect("checked above");
+            return *inner.downcast::<HttpError>().expect("checked above");
+        }
+        HttpError::Io(error)
+    }
+}
+
+impl From<HttpError> for std::io::Error {
+    fn from(error: HttpError) -> Self {
+        match error {
+            HttpError::Io(error) => error,
+            error => std::io::Error::other(error),
+        }
+    }
+}
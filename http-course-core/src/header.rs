@@ -0,0 +1,185 @@
+use std::fmt;
+
+use crate::{HttpError, Result};
+
+/// The header fields of a request or a response.
+///
+/// Names are compared case-insensitively, the order in which the fields were
+/// added is preserved and a name can appear several times, like the
+/// `Set-Cookie` fields of a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of fields, counting every value of a repeated name.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sets the value of `name`, replacing all its previous values.
+    ///
+    /// The field keeps the position of the first previous value, if any.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.position(&name) {
+            Some(position) => {
+                self.entries[position] = (name.clone(), value);
+                let mut index = 0;
+                self.entries.retain(|(key, _)| {
+                    index += 1;
+                    index - 1 <= position || !key.eq_ignore_ascii_case(&name)
+                });
+            }
+            None => self.entries.push((name, value)),
+        }
+    }
+
+    /// Adds a value for `name`, keeping its previous values.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    /// Returns the first value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns every value of `name`, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Removes every value of `name` and returns them.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.entries.retain(|(key, value)| {
+            if key.eq_ignore_ascii_case(name) {
+                removed.push(value.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Iterates over all the fields as `(name, value)`, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the elements of a comma-separated list field, combining all
+    /// the values of `name`.
+    pub fn get_list<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.get_all(name)
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|element| !element.is_empty())
+    }
+
+    /// Tells whether the comma-separated list field `name` contains `token`,
+    /// ignoring case, like `close` in `Connection: keep-alive, close`.
+    pub fn contains_token(&self, name: &str, token: &str) -> bool {
+        self.get_list(name)
+            .any(|element| element.eq_ignore_ascii_case(token))
+    }
+
+    /// The value of `Content-Length`, if present.
+    ///
+    /// Repeated values are accepted only when they are all the same.
+    pub fn content_length(&self) -> Result<Option<u64>> {
+        let mut length = None;
+        for value in self.get_list("Content-Length") {
+            let invalid = || HttpError::InvalidHeader(format!("Content-Length: {}", value));
+            if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            let value: u64 = value.parse().map_err(|_| invalid())?;
+            if length.is_some_and(|length| length != value) {
+                return Err(invalid());
+            }
+            length = Some(value);
+        }
+        Ok(length)
+    }
+
+    /// Tells whether `chunked` is the last coding of `Transfer-Encoding`.
+    pub fn is_chunked(&self) -> bool {
+        self.get_list("Transfer-Encoding")
+            .last()
+            .is_some_and(|coding| coding.eq_ignore_ascii_case("chunked"))
+    }
+
+    /// The codings of `Content-Encoding`, in the order they were applied.
+    pub fn content_encoding(&self) -> Vec<&str> {
+        self.get_list("Content-Encoding").collect()
+    }
+
+    /// The value of `Content-Type`, if present.
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("Content-Type")
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.extend(iter);
+        headers
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for HeaderMap {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.append(name, value);
+        }
+    }
+}
+
+impl fmt::Display for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in self {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        Ok(())
+    }
+}
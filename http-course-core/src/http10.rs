@@ -0,0 +1,49 @@
+//! Helpers for the HTTP/1.0 clients, where the server closes the
+//! connection after every response.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::response::{parse_header_line, parse_status_line};
+use crate::{HttpError, HttpRequest, HttpResponse, Result};
+
+/// Sends `request` as HTTP/1.0 over a freshly opened stream and reads the
+/// response.
+pub async fn send<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    request: &HttpRequest,
+) -> Result<HttpResponse> {
+    stream.write_all(&request.to_bytes("HTTP/1.0")).await?;
+    stream.flush().await?;
+    read_response(stream).await
+}
+
+/// Reads the whole response until the server closes the connection.
+pub async fn read_response<S: AsyncRead + Unpin>(stream: &mut S) -> Result<HttpResponse> {
+    // read response
+    let mut raw = Vec::new();
+    let _ = stream.read_to_end(&mut raw).await?;
+
+    // the head ends with an empty line, everything after it is the body
+    let head_end = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or(HttpError::TruncatedHead)?;
+    let head = String::from_utf8_lossy(&raw[..head_end]);
+
+    let mut response = HttpResponse::default();
+    let mut line_iter = head.split("\r\n");
+    // first line
+    let first_line = line_iter.next().unwrap_or_default();
+    (_, response.status) = parse_status_line(first_line)?;
+
+    // headers
+    for header in line_iter {
+        let (key, value) = parse_header_line(header)?;
+        response.headers.append(key, value);
+    }
+
+    // body
+    response.body = raw[head_end + 4..].to_vec();
+    response.decode_body()?;
+    Ok(response)
+}
@@ -0,0 +1,45 @@
+//! Building blocks for the HTTP clients written during the course.
+//!
+//! The day binaries are thin consumers of this crate: they build an
+//! [`HttpRequest`], send it over an [`HttpConnection`] or with an
+//! [`HttpClient`], which keeps a pool of connections, and print the
+//! [`HttpResponse`].
+
+mod body;
+mod chunked;
+mod client;
+mod connection;
+mod connector;
+mod cookie;
+mod error;
+mod header;
+pub mod http10;
+mod method;
+mod origin;
+mod proxy;
+mod redirect;
+mod request;
+mod resolver;
+mod response;
+mod retry;
+mod tls;
+mod transport;
+
+pub use body::ResponseBody;
+pub use chunked::ChunkExtension;
+pub use client::HttpClient;
+pub use connection::{HttpConnection, StreamingResponse};
+pub use connector::Connector;
+pub use cookie::{Cookie, CookieJar, SameSite};
+pub use error::{HttpError, Result, TimeoutKind};
+pub use header::HeaderMap;
+pub use method::HttpMethod;
+pub use origin::Origin;
+pub use proxy::Proxy;
+pub use redirect::RedirectPolicy;
+pub use request::HttpRequest;
+pub use resolver::{CachingResolver, Resolver, Resolving, StaticResolver, SystemResolver};
+pub use response::HttpResponse;
+pub use retry::RetryPolicy;
+pub use tls::{ClientIdentity, TlsConfig, TlsInfo};
+pub use transport::MaybeTlsStream;
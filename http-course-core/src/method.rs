@@ -0,0 +1,27 @@
+use strum_macros::Display;
+
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    #[strum(serialize = "GET")]
+    Get,
+    #[strum(serialize = "HEAD")]
+    Head,
+    #[strum(serialize = "POST")]
+    Post,
+    #[strum(serialize = "PUT")]
+    Put,
+    #[strum(serialize = "PATCH")]
+    Patch,
+    #[strum(serialize = "DELETE")]
+    Delete,
+    #[strum(serialize = "OPTIONS")]
+    Options,
+}
+
+impl HttpMethod {
+    /// Tells whether sending the request twice has the same effect as
+    /// sending it once, so it can be retried safely (RFC 9110, 9.2.2).
+    pub fn is_idempotent(self) -> bool {
+        !matches!(self, HttpMethod::Post | HttpMethod::Patch)
+    }
+}
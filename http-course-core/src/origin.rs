@@ -0,0 +1,103 @@
+use percent_encoding::percent_decode_str;
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::PathBuf;
+use tokio_rustls::rustls::pki_types::ServerName;
+use url::{Host, Url};
+
+use crate::{HttpError, Result};
+
+/// The scheme of the urls of servers listening on a Unix socket, whose
+/// percent-encoded path is the host: `http+unix://%2Fvar%2Frun%2Fdocker.sock/info`.
+pub(crate) const UNIX_SCHEME: &str = "http+unix";
+
+/// The scheme, host and port of a url: connections to the same origin can
+/// be shared.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    scheme: String,
+    host: String,
+    port: u16,
+}
+
+impl Origin {
+    /// The host is written the way it appears in a url: international
+    /// domain names in punycode and IPv6 addresses in brackets.
+    pub fn new(scheme: impl Into<String>, host: impl Into<String>, port: u16) -> Self {
+        let scheme = scheme.into().to_ascii_lowercase();
+        let host = host.into();
+        let host = if scheme == UNIX_SCHEME {
+            // a path, where case matters
+            host
+        } else if let Ok(address) = host.parse::<Ipv6Addr>() {
+            format!("[{}]", address)
+        } else {
+            match Host::parse(&host) {
+                Ok(parsed) => parsed.to_string(),
+                Err(_) => host.to_ascii_lowercase(),
+            }
+        };
+        Self { scheme, host, port }
+    }
+
+    /// The origin of `url`, with the default port of the scheme when the url
+    /// has none, and port 0 for a Unix socket.
+    pub fn from_url(url: &Url) -> Result<Self> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| HttpError::InvalidUrl(url.to_string()))?;
+        let port = match url.port_or_known_default() {
+            Some(port) => port,
+            None if url.scheme() == UNIX_SCHEME => 0,
+            None => return Err(HttpError::InvalidUrl(url.to_string())),
+        };
+        Ok(Self::new(url.scheme(), host, port))
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The name the server is verified against: a domain name is sent with
+    /// SNI, an IP address is matched with the IP SANs of the certificate.
+    pub(crate) fn server_name(&self) -> Result<ServerName<'static>> {
+        ServerName::try_from(self.bare_host().to_string())
+            .map_err(|_| HttpError::InvalidUrl(self.to_string()))
+    }
+
+    /// The address of the host, when the url has one instead of a name.
+    pub(crate) fn ip_address(&self) -> Option<IpAddr> {
+        self.bare_host().parse().ok()
+    }
+
+    /// The path of the socket, for a `http+unix` origin.
+    pub(crate) fn unix_socket_path(&self) -> Option<PathBuf> {
+        if self.scheme != UNIX_SCHEME {
+            return None;
+        }
+        let path = percent_decode_str(&self.host).decode_utf8_lossy();
+        Some(PathBuf::from(path.into_owned()))
+    }
+
+    /// The host without the brackets of an IPv6 address.
+    pub(crate) fn bare_host(&self) -> &str {
+        self.host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(&self.host)
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}:{}", self.scheme, self.host, self.port)
+    }
+}
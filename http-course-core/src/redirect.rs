@@ -0,0 +1,120 @@
+use crate::origin::UNIX_SCHEME;
+use crate::{HttpError, HttpMethod, HttpRequest, HttpResponse, Origin, Result};
+
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Which redirects [`HttpClient`](crate::HttpClient) follows.
+///
+/// A redirect that the policy does not allow is not an error: the 3xx
+/// response is returned as-is, with its `Location` header. Going past
+/// [`RedirectPolicy::max_redirects`] fails with
+/// [`HttpError::TooManyRedirects`] instead, as it usually means a loop.
+#[derive(Debug, Clone)]
+pub struct RedirectPolicy {
+    max_redirects: usize,
+    same_origin_only: bool,
+    allow_downgrade: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            same_origin_only: false,
+            allow_downgrade: false,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// Follows up to 10 redirects, to any origin, but never from HTTPS to
+    /// HTTP.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Does not follow redirects at all.
+    pub fn none() -> Self {
+        Self::new().max_redirects(0)
+    }
+
+    /// The number of redirects followed for a single request.
+    pub fn max_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = max;
+        self
+    }
+
+    /// Only follows redirects to the origin of the previous request.
+    pub fn same_origin_only(mut self, same_origin_only: bool) -> Self {
+        self.same_origin_only = same_origin_only;
+        self
+    }
+
+    /// Follows redirects from `https` to `http` urls.
+    pub fn allow_downgrade(mut self, allow_downgrade: bool) -> Self {
+        self.allow_downgrade = allow_downgrade;
+        self
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.max_redirects
+    }
+
+    /// The request that follows the redirect in `response` to `request`, or
+    /// `None` when the response is not a redirect or the policy does not
+    /// allow it.
+    pub(crate) fn follow(
+        &self,
+        request: &HttpRequest,
+        response: &HttpResponse,
+    ) -> Result<Option<HttpRequest>> {
+        if self.max_redirects == 0 || !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+            return Ok(None);
+        }
+        let Some(location) = response.headers.get("Location") else {
+            return Ok(None);
+        };
+        // the location may be relative to the url of the request
+        let uri = request
+            .uri
+            .join(location)
+            .map_err(|_| HttpError::InvalidHeader(format!("Location: {}", location)))?;
+        // a remote server must not send the client to a local socket
+        let unix = uri.scheme() == UNIX_SCHEME && request.uri.scheme() == UNIX_SCHEME;
+        if !matches!(uri.scheme(), "http" | "https") && !unix {
+            return Ok(None);
+        }
+        if request.uri.scheme() == "https" && uri.scheme() == "http" && !self.allow_downgrade {
+            return Ok(None);
+        }
+        let same_origin = Origin::from_url(&request.uri)? == Origin::from_url(&uri)?;
+        if self.same_origin_only && !same_origin {
+            return Ok(None);
+        }
+
+        let mut next = request.clone();
+        next.uri = uri;
+        // browsers send a GET after a 303, and after a 301 or 302 to a POST;
+        // 307 and 308 keep the method and the body
+        let to_get = match response.status {
+            303 => request.method != HttpMethod::Head,
+            301 | 302 => request.method == HttpMethod::Post,
+            _ => false,
+        };
+        if to_get {
+            next.method = HttpMethod::Get;
+            next.body.clear();
+            for name in ["Content-Length", "Content-Type", "Content-Encoding"] {
+                next.headers.remove(name);
+            }
+        }
+        if !same_origin {
+            // credentials and the host are meant for the previous origin only;
+            // the cookies of the jar are chosen again for the new one
+            for name in ["Authorization", "Cookie", "Proxy-Authorization", "Host"] {
+                next.headers.remove(name);
+            }
+        }
+        Ok(Some(next))
+    }
+}
@@ -0,0 +1,229 @@
+use url::Url;
+use url::form_urlencoded;
+
+use crate::origin::UNIX_SCHEME;
+use crate::{HeaderMap, HttpMethod};
+
+/// An HTTP request, built with a constructor per method and then the
+/// setters below:
+///
+/// ```no_run
+/// # use http_course_core::HttpRequest;
+/// # use url::Url;
+/// let request = HttpRequest::post(Url::parse("https://example.com/echo").unwrap())
+///     .header("accept", "text/plain")
+///     .query("verbose", "1")
+///     .form(&[("name", "pippo"), ("age", "3")]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub uri: Url,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: HttpMethod, uri: Url) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-encoding", "gzip");
+        let body: Vec<u8> = Vec::new();
+        Self {
+            method,
+            uri,
+            headers,
+            body,
+        }
+    }
+
+    pub fn get(uri: Url) -> Self {
+        Self::new(HttpMethod::Get, uri)
+    }
+
+    pub fn head(uri: Url) -> Self {
+        Self::new(HttpMethod::Head, uri)
+    }
+
+    pub fn post(uri: Url) -> Self {
+        Self::new(HttpMethod::Post, uri)
+    }
+
+    pub fn put(uri: Url) -> Self {
+        Self::new(HttpMethod::Put, uri)
+    }
+
+    pub fn patch(uri: Url) -> Self {
+        Self::new(HttpMethod::Patch, uri)
+    }
+
+    pub fn delete(uri: Url) -> Self {
+        Self::new(HttpMethod::Delete, uri)
+    }
+
+    pub fn options(uri: Url) -> Self {
+        Self::new(HttpMethod::Options, uri)
+    }
+
+    /// Sets the header `name`, replacing any previous value.
+    ///
+    /// Line breaks are removed from the name and turned into spaces in the
+    /// value, so that neither can end the header line.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(field_name(name), field_value(value));
+        self
+    }
+
+    /// Adds a value for the header `name`, keeping the previous ones, with
+    /// line breaks removed like in [`HttpRequest::header`].
+    pub fn append_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.append(field_name(name), field_value(value));
+        self
+    }
+
+    /// Appends a `name=value` pair to the query string of the url.
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        self.uri.query_pairs_mut().append_pair(name, value);
+        self
+    }
+
+    /// Sets the body and its `Content-Length`.
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self.headers
+            .insert("content-length", self.body.len().to_string());
+        self
+    }
+
+    /// Sets a `text/plain` body.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.header("content-type", "text/plain; charset=utf-8")
+            .body(text.into())
+    }
+
+    /// Sets an `application/x-www-form-urlencoded` body made of `pairs`.
+    pub fn form(self, pairs: &[(&str, &str)]) -> Self {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(pairs)
+            .finish();
+        self.header("content-type", "application/x-www-form-urlencoded")
+            .body(body)
+    }
+
+    /// Tells the server that trailer fields are welcome after a chunked
+    /// body, by sending `TE: trailers`.
+    pub fn with_trailers(mut self) -> Self {
+        self.headers.insert("te", "trailers");
+        // TE is hop-by-hop, so it must be listed in the Connection header,
+        // next to the options the caller may have set there
+        if !self.headers.contains_token("connection", "TE") {
+            let mut options: Vec<String> = self
+                .headers
+                .get_list("connection")
+                .map(String::from)
+                .collect();
+            options.push("TE".to_string());
+            self.headers.insert("connection", options.join(", "));
+        }
+        self
+    }
+
+    pub fn relative_url(&self) -> String {
+        let path = &self.uri.path();
+        let query = &self.uri.query();
+        match query {
+            Some(query) => format!("{path}?{query}"),
+            None => path.to_string(),
+        }
+    }
+
+    /// The value of the `Host` header: the one set by the caller, if any,
+    /// otherwise the host of the url, followed by its port when it is not
+    /// the default one of the scheme.
+    pub fn host(&self) -> String {
+        match self.headers.get("host") {
+            Some(host) => host.to_string(),
+            None => self.authority(),
+        }
+    }
+
+    /// The host of the url, with its port when it is not the default one.
+    fn authority(&self) -> String {
+        // the path of a Unix socket means nothing to the server
+        if self.uri.scheme() == UNIX_SCHEME {
+            return "localhost".to_string();
+        }
+        // IPv6 addresses are already in brackets, domains already in punycode
+        let host = self.uri.host_str().unwrap_or_default();
+        match self.uri.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        }
+    }
+
+    /// Serializes the request line, the headers and the body for the given
+    /// protocol version, like `HTTP/1.1`.
+    pub(crate) fn to_bytes(&self, version: &str) -> Vec<u8> {
+        self.serialize(version, &self.relative_url(), None)
+    }
+
+    /// Serializes the request for a forward proxy: the target is the whole
+    /// url, without its credentials and fragment.
+    pub(crate) fn to_proxy_bytes(&self, version: &str, authorization: Option<&str>) -> Vec<u8> {
+        let target = format!(
+            "{}://{}{}",
+            self.uri.scheme(),
+            self.authority(),
+            self.relative_url()
+        );
+        self.serialize(version, &target, authorization)
+    }
+
+    fn serialize(&self, version: &str, target: &str, proxy_authorization: Option<&str>) -> Vec<u8> {
+        // first line
+        let mut bytes = format!("{} {} {}\r\n", self.method, target, version).into_bytes();
+
+        // headers
+        if !self.headers.contains("host") {
+            bytes.extend_from_slice(format!("host: {}\r\n", self.host()).as_bytes());
+        }
+        if let Some(authorization) = proxy_authorization
+            && !self.headers.contains("proxy-authorization")
+        {
+            bytes.extend_from_slice(
+                format!("proxy-authorization: {}\r\n", authorization).as_bytes(),
+            );
+        }
+        // a Connection header set after `with_trailers` must still list TE
+        let mut missing_te =
+            self.headers.contains("te") && !self.headers.contains_token("connection", "TE");
+        for (key, value) in &self.headers {
+            if missing_te && key.eq_ignore_ascii_case("connection") {
+                bytes.extend_from_slice(format!("{}: {}, TE\r\n", key, value).as_bytes());
+                missing_te = false;
+                continue;
+            }
+            bytes.extend_from_slice(format!("{}: {}\r\n", key, value).as_bytes());
+        }
+        if missing_te {
+            bytes.extend_from_slice(b"connection: TE\r\n");
+        }
+        bytes.extend_from_slice(b"\r\n");
+
+        // body
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+/// A header name without the CR, LF and NUL characters.
+fn field_name(name: impl Into<String>) -> String {
+    let mut name = name.into();
+    name.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+    name
+}
+
+/// A header value with its CR, LF and NUL characters replaced by spaces, as
+/// RFC 9110, section 5.5 allows.
+fn field_value(value: impl Into<String>) -> String {
+    value.into().replace(['\r', '\n', '\0'], " ")
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::Result;
+
+/// The future returned by [`Resolver::resolve`].
+pub type Resolving<'a> = Pin<Box<dyn Future<Output = Result<Vec<SocketAddr>>> + Send + 'a>>;
+
+/// Turns the host of a url into the addresses the [`Connector`](crate::Connector)
+/// tries, in order.
+///
+/// The host is a domain name in punycode; IP addresses in the url are
+/// connected to directly, without a resolver.
+pub trait Resolver: Debug + Send + Sync {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> Resolving<'a>;
+}
+
+/// Asks the operating system, like `TcpStream::connect` does.
+#[derive(Debug, Clone, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> Resolving<'a> {
+        Box::pin(async move { Ok(tokio::net::lookup_host((host, port)).await?.collect()) })
+    }
+}
+
+/// Resolves some hosts to fixed addresses, like curl `--resolve`, and the
+/// other hosts with a fallback resolver, the [`SystemResolver`] by default.
+///
+/// ```no_run
+/// # use http_course_core::{HttpClient, StaticResolver};
+/// // reach a local stand-in under the real name, TLS included
+/// let resolver = StaticResolver::new().insert("gioyingtec.com", [127, 0, 0, 1].into());
+/// let client = HttpClient::new().resolver(resolver);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StaticResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    fallback: Arc<dyn Resolver>,
+}
+
+impl Default for StaticResolver {
+    fn default() -> Self {
+        Self {
+            hosts: HashMap::new(),
+            fallback: Arc::new(SystemResolver),
+        }
+    }
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves `host` to `address`; the addresses of the same host are
+    /// tried in the order they were inserted.
+    pub fn insert(mut self, host: &str, address: IpAddr) -> Self {
+        self.hosts
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .push(address);
+        self
+    }
+
+    /// Resolves the hosts that were not inserted with `resolver`.
+    pub fn fallback(mut self, resolver: impl Resolver + 'static) -> Self {
+        self.fallback = Arc::new(resolver);
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> Resolving<'a> {
+        match self.hosts.get(&host.to_ascii_lowercase()) {
+            Some(addresses) => {
+                let addresses = addresses
+                    .iter()
+                    .map(|address| SocketAddr::new(*address, port))
+                    .collect();
+                Box::pin(async move { Ok(addresses) })
+            }
+            None => self.fallback.resolve(host, port),
+        }
+    }
+}
+
+/// Remembers the addresses found by another resolver for `ttl`, so that
+/// the connections to the same host do not wait for a new lookup.
+///
+/// Failed lookups are not remembered.
+#[derive(Debug)]
+pub struct CachingResolver {
+    inner: Arc<dyn Resolver>,
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, u16), CacheEntry>>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    addresses: Vec<SocketAddr>,
+    expires_at: Instant,
+}
+
+impl CachingResolver {
+    pub fn new(inner: impl Resolver + 'static, ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forgets every address.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl Resolver for CachingResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> Resolving<'a> {
+        Box::pin(async move {
+            let key = (host.to_ascii_lowercase(), port);
+            let now = Instant::now();
+            {
+                let mut entries = self.entries.lock().unwrap();
+                entries.retain(|_, entry| entry.expires_at > now);
+                if let Some(entry) = entries.get(&key) {
+                    return Ok(entry.addresses.clone());
+                }
+            }
+            let addresses = self.inner.resolve(host, port).await?;
+            self.entries.lock().unwrap().insert(
+                key,
+                CacheEntry {
+                    addresses: addresses.clone(),
+                    expires_at: Instant::now() + self.ttl,
+                },
+            );
+            Ok(addresses)
+        })
+    }
+}
+
+/// The error of a lookup that found nothing.
+pub(crate) fn no_addresses(host: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no address found for {}", host),
+    )
+}
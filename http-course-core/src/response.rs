@@ -0,0 +1,86 @@
+use flate2::read::GzDecoder;
+use std::fmt;
+use std::io::Read;
+use url::Url;
+
+use crate::{ChunkExtension, HeaderMap, HttpError, Result, TlsInfo};
+
+#[derive(Default, Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Extensions sent with the chunks of a chunked body.
+    pub chunk_extensions: Vec<ChunkExtension>,
+    /// Trailer fields sent after the last chunk of a chunked body.
+    pub trailers: HeaderMap,
+    /// The urls the request was redirected to, in order: the response comes
+    /// from the last one. Empty when no redirect was followed.
+    pub redirects: Vec<Url>,
+    /// What was negotiated during the TLS handshake, for `https` urls.
+    pub tls_info: Option<TlsInfo>,
+}
+
+impl HttpResponse {
+    /// Decompresses a gzipped body in place.
+    pub(crate) fn decode_body(&mut self) -> Result<()> {
+        if let [coding] = self.headers.content_encoding().as_slice()
+            && coding.eq_ignore_ascii_case("gzip")
+        {
+            let mut decoder = GzDecoder::new(self.body.as_slice());
+            let mut uncompressed_body = Vec::new();
+            decoder
+                .read_to_end(&mut uncompressed_body)
+                .map_err(HttpError::Decompression)?;
+            self.body = uncompressed_body;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "status = {}", self.status)?;
+        writeln!(f)?;
+        writeln!(f, "headers:")?;
+        write!(f, "{}", self.headers)?;
+        writeln!(f)?;
+        writeln!(f, "body:")?;
+        write!(f, "{}", String::from_utf8_lossy(&self.body))?;
+        if !self.trailers.is_empty() {
+            writeln!(f)?;
+            writeln!(f)?;
+            writeln!(f, "trailers:")?;
+            write!(f, "{}", self.trailers)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a status line like `HTTP/1.1 200 OK` and returns the protocol
+/// version and the status code.
+pub(crate) fn parse_status_line(line: &str) -> Result<(&str, u16)> {
+    let mut tokens = line.split_whitespace();
+    let malformed = || HttpError::MalformedStatusLine(line.to_string());
+    let protocol = tokens.next().ok_or_else(malformed)?;
+    if !protocol.starts_with("HTTP/") {
+        return Err(malformed());
+    }
+    let status = tokens.next().ok_or_else(malformed)?;
+    let status = status.parse().map_err(|_| malformed())?;
+    Ok((protocol, status))
+}
+
+/// Parses a `name: value` header line.
+///
+/// Whitespace around the name is refused (RFC 9112, 5.1): a proxy that
+/// ignored it could read another message length than this client.
+pub(crate) fn parse_header_line(line: &str) -> Result<(String, String)> {
+    let (key, value) = line
+        .split_once(":")
+        .ok_or_else(|| HttpError::InvalidHeader(line.to_string()))?;
+    if key.is_empty() || key.bytes().any(|byte| byte.is_ascii_whitespace()) {
+        return Err(HttpError::InvalidHeader(line.trim_end().to_string()));
+    }
+    Ok((key.to_string(), value.trim().to_string()))
+}
@@ -0,0 +1,151 @@
+use std::io::ErrorKind;
+use std::time::{Duration, SystemTime};
+
+use crate::{HttpError, HttpMethod, HttpResponse, Result, TimeoutKind};
+
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_ELAPSED: Duration = Duration::from_secs(30);
+const DEFAULT_RETRY_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+/// When and how often [`HttpClient`](crate::HttpClient) sends a request
+/// again after a failure.
+///
+/// Only idempotent methods are retried, after a connection error or a
+/// response with one of the retry statuses. The attempts are spaced by an
+/// exponential backoff with jitter, or by the `Retry-After` header of the
+/// response. When no attempt is left, the last response or error is
+/// returned.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_elapsed: Duration,
+    retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            max_elapsed: DEFAULT_MAX_ELAPSED,
+            retry_statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Up to 3 attempts within 30 seconds, starting with a 100 ms backoff,
+    /// retrying 429, 502, 503 and 504 responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of times the request is sent, the first one included.
+    pub fn max_attempts(mut self, max: usize) -> Self {
+        self.max_attempts = max.max(1);
+        self
+    }
+
+    /// The backoff before the second attempt; it doubles at each attempt.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// The longest backoff between two attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// No attempt is made after `elapsed` since the first one, nor one that
+    /// would have to wait past it.
+    pub fn max_elapsed(mut self, elapsed: Duration) -> Self {
+        self.max_elapsed = elapsed;
+        self
+    }
+
+    /// The response statuses that are worth a new attempt.
+    pub fn retry_statuses(mut self, statuses: &[u16]) -> Self {
+        self.retry_statuses = statuses.to_vec();
+        self
+    }
+
+    pub(crate) fn max_attempts_for(&self, method: HttpMethod) -> usize {
+        if method.is_idempotent() {
+            self.max_attempts
+        } else {
+            1
+        }
+    }
+
+    pub(crate) fn max_elapsed_time(&self) -> Duration {
+        self.max_elapsed
+    }
+
+    /// Tells whether the outcome of an attempt is worth a new one.
+    pub(crate) fn should_retry(&self, outcome: &Result<HttpResponse>) -> bool {
+        match outcome {
+            Ok(response) => self.retry_statuses.contains(&response.status),
+            Err(error) => is_connection_error(error),
+        }
+    }
+
+    /// The delay before the attempt that follows `attempt` (counted from 1).
+    pub(crate) fn delay(&self, attempt: usize, response: Option<&HttpResponse>) -> Duration {
+        if let Some(retry_after) = response.and_then(retry_after) {
+            return retry_after;
+        }
+        let exponent = u32::try_from(attempt - 1).unwrap_or(u32::MAX).min(31);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        // half of the backoff is random, so that clients that failed together
+        // do not retry together
+        let half = backoff / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+/// Errors that leave the server in a state where a new attempt can
+/// succeed: the connection failed, not the exchange.
+///
+/// Other I/O errors, like a certificate the TLS handshake refused, would
+/// fail the same way again.
+fn is_connection_error(error: &HttpError) -> bool {
+    match error {
+        HttpError::ConnectionClosed | HttpError::StaleConnection | HttpError::TruncatedHead => true,
+        HttpError::Io(error) => matches!(
+            error.kind(),
+            ErrorKind::ConnectionRefused
+                | ErrorKind::HostUnreachable
+                | ErrorKind::NetworkUnreachable
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof
+        ),
+        HttpError::Timeout(kind) => *kind != TimeoutKind::Request,
+        _ => false,
+    }
+}
+
+/// The delay asked by the `Retry-After` header, in seconds or as an
+/// HTTP-date.
+fn retry_after(response: &HttpResponse) -> Option<Duration> {
+    let value = response.headers.get("Retry-After")?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
@@ -0,0 +1,527 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::client::WebPkiServerVerifier;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{
+    CryptoProvider, verify_tls12_signature, verify_tls13_signature,
+};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{
+    CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime,
+};
+use tokio_rustls::rustls::sign::{CertifiedKey, SingleCertAndKey};
+use tokio_rustls::rustls::{
+    CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct, ProtocolVersion,
+    RootCertStore, SignatureScheme,
+};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::{HttpError, Origin, Result};
+
+/// Which servers are trusted by the TLS connections of a
+/// [`Connector`](crate::Connector).
+///
+/// By default, the certificates must chain to one of the Mozilla root
+/// certificates bundled with `webpki-roots`. More roots can be added from
+/// PEM files, from the trust store of the operating system, or the
+/// verification can be disabled for local development.
+///
+/// A [`ClientIdentity`] is presented to the servers that ask for a client
+/// certificate, either to all of them or to a given origin.
+///
+/// An origin can also be pinned to the keys of its certificates, so that
+/// the handshake fails when the server presents another key, even with a
+/// certificate signed by a trusted CA.
+///
+/// ```no_run
+/// # use http_course_core::{Connector, TlsConfig};
+/// # fn run() -> http_course_core::Result<()> {
+/// let tls = TlsConfig::new()
+///     .webpki_roots(false)
+///     .add_ca_file("/etc/internal/ca.pem")?;
+/// let connector = Connector::new().tls(tls);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    webpki_roots: bool,
+    /// Roots loaded from files or from the operating system.
+    roots: Vec<CertificateDer<'static>>,
+    accept_invalid_certs: bool,
+    identity: Option<ClientIdentity>,
+    origin_identities: HashMap<Origin, ClientIdentity>,
+    /// SHA-256 hashes of the accepted public keys, per origin.
+    pins: HashMap<Origin, Vec<[u8; 32]>>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            webpki_roots: true,
+            roots: Vec::new(),
+            accept_invalid_certs: false,
+            identity: None,
+            origin_identities: HashMap::new(),
+            pins: HashMap::new(),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Trusts the Mozilla root certificates only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts the Mozilla root certificates bundled with `webpki-roots`, or
+    /// not.
+    pub fn webpki_roots(mut self, enabled: bool) -> Self {
+        self.webpki_roots = enabled;
+        self
+    }
+
+    /// Trusts the CA certificates of a PEM file.
+    pub fn add_ca_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let certificates = read_pem_certificates(path.as_ref())?;
+        if certificates.is_empty() {
+            return Err(HttpError::Certificate(format!(
+                "no certificate in {}",
+                path.as_ref().display()
+            )));
+        }
+        self.roots.extend(certificates);
+        Ok(self)
+    }
+
+    /// Trusts the CA certificates of all the PEM files of a directory, like
+    /// `/etc/ssl/certs`. Files without certificates are skipped.
+    pub fn add_ca_dir(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut found = false;
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            if !file.is_file() {
+                continue;
+            }
+            if let Ok(certificates) = read_pem_certificates(&file) {
+                found |= !certificates.is_empty();
+                self.roots.extend(certificates);
+            }
+        }
+        if !found {
+            return Err(HttpError::Certificate(format!(
+                "no certificate in {}",
+                path.display()
+            )));
+        }
+        Ok(self)
+    }
+
+    /// Trusts a CA certificate in DER form.
+    pub fn add_ca_certificate(mut self, certificate: CertificateDer<'static>) -> Self {
+        self.roots.push(certificate);
+        self
+    }
+
+    /// Trusts the certificates of the trust store of the operating system.
+    pub fn native_roots(mut self) -> Result<Self> {
+        let native = rustls_native_certs::load_native_certs();
+        if native.certs.is_empty()
+            && let Some(error) = native.errors.into_iter().next()
+        {
+            return Err(HttpError::Certificate(format!(
+                "cannot load the system certificates: {}",
+                error
+            )));
+        }
+        self.roots.extend(native.certs);
+        Ok(self)
+    }
+
+    /// Accepts any certificate, whoever signed it and whatever name it is
+    /// for.
+    ///
+    /// This makes the connections open to man-in-the-middle attacks: only
+    /// use it against a local server with a self-signed certificate.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Presents `identity` to the servers that ask for a client certificate.
+    pub fn identity(mut self, identity: ClientIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Presents `identity` to `origin` instead of the identity set with
+    /// [`TlsConfig::identity`], if any.
+    pub fn identity_for(mut self, origin: Origin, identity: ClientIdentity) -> Self {
+        self.origin_identities.insert(origin, identity);
+        self
+    }
+
+    /// Pins `origin` to the keys whose SubjectPublicKeyInfo hashes to one of
+    /// `pins`, base64-encoded SHA-256 hashes with an optional `sha256/`
+    /// prefix, like the ones printed by [`TlsInfo::spki_sha256`].
+    ///
+    /// The pins are matched against the key of the certificate of the
+    /// server only: the other certificates it sends are not all part of the
+    /// verified chain, so pinning them would not prove anything.
+    pub fn pin_sha256(mut self, origin: Origin, pins: &[&str]) -> Result<Self> {
+        let mut hashes = Vec::new();
+        for pin in pins {
+            let encoded = pin.strip_prefix("sha256/").unwrap_or(pin);
+            let hash = BASE64
+                .decode(encoded)
+                .ok()
+                .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                .ok_or_else(|| HttpError::Certificate(format!("invalid pin {}", pin)))?;
+            hashes.push(hash);
+        }
+        self.pins.entry(origin).or_default().extend(hashes);
+        Ok(self)
+    }
+
+    /// The origins with their own identity or pins.
+    pub(crate) fn custom_origins(&self) -> HashSet<&Origin> {
+        self.origin_identities
+            .keys()
+            .chain(self.pins.keys())
+            .collect()
+    }
+
+    /// Builds the rustls configuration, for the streams that are not opened
+    /// by a [`Connector`](crate::Connector).
+    pub fn client_config(&self) -> Arc<ClientConfig> {
+        self.build(self.identity.as_ref(), None)
+    }
+
+    /// Builds the rustls configuration for the connections to `origin`.
+    pub fn client_config_for(&self, origin: &Origin) -> Arc<ClientConfig> {
+        self.build(
+            self.origin_identities
+                .get(origin)
+                .or(self.identity.as_ref()),
+            self.pins.get(origin),
+        )
+    }
+
+    fn build(
+        &self,
+        identity: Option<&ClientIdentity>,
+        pins: Option<&Vec<[u8; 32]>>,
+    ) -> Arc<ClientConfig> {
+        let builder = ClientConfig::builder();
+        let builder = match pins {
+            Some(pins) => {
+                let provider = crypto_provider();
+                let inner: Option<Arc<dyn ServerCertVerifier>> = if self.accept_invalid_certs {
+                    Some(Arc::new(NoVerification(provider.clone())))
+                } else {
+                    // fails when there is no root at all
+                    WebPkiServerVerifier::builder_with_provider(
+                        Arc::new(self.root_cert_store()),
+                        provider.clone(),
+                    )
+                    .build()
+                    .ok()
+                    .map(|verifier| verifier as Arc<dyn ServerCertVerifier>)
+                };
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(PinningVerifier {
+                        inner,
+                        provider,
+                        pins: pins.clone(),
+                    }))
+            }
+            None if self.accept_invalid_certs => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerification(crypto_provider()))),
+            None => builder.with_root_certificates(self.root_cert_store()),
+        };
+        let mut config = match identity {
+            Some(identity) => builder
+                .with_client_cert_resolver(Arc::new(SingleCertAndKey::from(identity.key.clone()))),
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Arc::new(config)
+    }
+
+    fn root_cert_store(&self) -> RootCertStore {
+        let mut root_cert_store = RootCertStore::empty();
+        if self.webpki_roots {
+            root_cert_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        // the system stores often contain a few certificates that are not
+        // valid trust anchors
+        root_cert_store.add_parsable_certificates(self.roots.iter().cloned());
+        root_cert_store
+    }
+}
+
+/// What was negotiated during the TLS handshake.
+#[derive(Debug, Clone)]
+pub struct TlsInfo {
+    /// Like `TLSv1.3`.
+    pub protocol_version: String,
+    /// Like `TLS13_AES_256_GCM_SHA384`.
+    pub cipher_suite: String,
+    /// The protocol chosen by the server with ALPN, if any.
+    pub alpn_protocol: Option<String>,
+    /// The certificates sent by the server, its own first.
+    pub peer_certificates: Vec<CertificateDer<'static>>,
+}
+
+impl TlsInfo {
+    pub(crate) fn from_connection(connection: &ClientConnection) -> Self {
+        let protocol_version = match connection.protocol_version() {
+            Some(ProtocolVersion::TLSv1_2) => "TLSv1.2".to_string(),
+            Some(ProtocolVersion::TLSv1_3) => "TLSv1.3".to_string(),
+            Some(version) => format!("{:?}", version),
+            None => String::new(),
+        };
+        let cipher_suite = connection
+            .negotiated_cipher_suite()
+            .map(|suite| format!("{:?}", suite.suite()))
+            .unwrap_or_default();
+        Self {
+            protocol_version,
+            cipher_suite,
+            alpn_protocol: connection
+                .alpn_protocol()
+                .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+            peer_certificates: connection
+                .peer_certificates()
+                .map(|certificates| {
+                    certificates
+                        .iter()
+                        .map(|c| c.clone().into_owned())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The base64-encoded SHA-256 hash of the public key of the server, to
+    /// be used with [`TlsConfig::pin_sha256`].
+    pub fn spki_sha256(&self) -> Option<String> {
+        let certificate = self.peer_certificates.first()?;
+        spki_sha256(certificate).map(|hash| BASE64.encode(hash))
+    }
+}
+
+/// A client certificate chain with its private key, for the servers that
+/// authenticate their clients (mutual TLS).
+///
+/// The key must match the first certificate of the chain, which is checked
+/// when the identity is created.
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    key: Arc<CertifiedKey>,
+}
+
+impl ClientIdentity {
+    /// Builds an identity from a certificate chain and a private key in DER
+    /// form, the client certificate first.
+    pub fn new(chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Result<Self> {
+        if chain.is_empty() {
+            return Err(HttpError::Certificate(
+                "empty client certificate chain".to_string(),
+            ));
+        }
+        let key = CertifiedKey::from_der(chain, key, &crypto_provider())
+            .map_err(|error| HttpError::Certificate(error.to_string()))?;
+        Ok(Self { key: Arc::new(key) })
+    }
+
+    /// Reads an identity from a PEM certificate chain and a PEM private key,
+    /// in PKCS#8, PKCS#1 or SEC1 form.
+    pub fn from_pem(chain: &[u8], key: &[u8]) -> Result<Self> {
+        let invalid = |error| HttpError::Certificate(format!("{}", error));
+        let chain = CertificateDer::pem_slice_iter(chain)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(invalid)?;
+        let key = PrivateKeyDer::from_pem_slice(key).map_err(invalid)?;
+        Self::new(chain, key)
+    }
+
+    /// Reads an identity from PEM files, see [`ClientIdentity::from_pem`].
+    pub fn from_pem_files(chain: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<Self> {
+        Self::from_pem(&std::fs::read(chain)?, &std::fs::read(key)?)
+    }
+
+    /// Builds an identity from a certificate chain and a PKCS#8 private key
+    /// in DER form.
+    pub fn from_pkcs8_der(chain: Vec<CertificateDer<'static>>, key: Vec<u8>) -> Result<Self> {
+        Self::new(chain, PrivatePkcs8KeyDer::from(key).into())
+    }
+
+    /// Reads the first private key and its certificate chain from a PKCS#12
+    /// archive (`.p12` or `.pfx`).
+    pub fn from_pkcs12(archive: &[u8], password: &str) -> Result<Self> {
+        let key_store = p12_keystore::KeyStore::from_pkcs12(archive, password)
+            .map_err(|error| HttpError::Certificate(format!("PKCS#12: {}", error)))?;
+        let (_, key_chain) = key_store.private_key_chain().ok_or_else(|| {
+            HttpError::Certificate("no private key in the PKCS#12 archive".to_string())
+        })?;
+        let chain = key_chain
+            .chain()
+            .iter()
+            .map(|certificate| CertificateDer::from(certificate.as_der().to_vec()))
+            .collect();
+        Self::from_pkcs8_der(chain, key_chain.key().to_vec())
+    }
+
+    /// Reads a PKCS#12 file, see [`ClientIdentity::from_pkcs12`].
+    pub fn from_pkcs12_file(path: impl AsRef<Path>, password: &str) -> Result<Self> {
+        Self::from_pkcs12(&std::fs::read(path)?, password)
+    }
+}
+
+fn read_pem_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let invalid = |error| HttpError::Certificate(format!("{}: {}", path.display(), error));
+    CertificateDer::pem_file_iter(path)
+        .map_err(invalid)?
+        .collect::<std::result::Result<_, _>>()
+        .map_err(invalid)
+}
+
+/// The SHA-256 hash of the SubjectPublicKeyInfo of a DER certificate.
+fn spki_sha256(certificate: &[u8]) -> Option<[u8; 32]> {
+    let (_, certificate) = X509Certificate::from_der(certificate).ok()?;
+    // the hash covers the whole element, tag and length included
+    Some(Sha256::digest(certificate.public_key().raw).into())
+}
+
+/// The crypto provider used by `ClientConfig::builder`.
+fn crypto_provider() -> Arc<CryptoProvider> {
+    CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(tokio_rustls::rustls::crypto::aws_lc_rs::default_provider()))
+}
+
+/// Accepts every server certificate, but still checks that the server owns
+/// it, so that the handshake fails on a broken server.
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Verifies the certificate with `inner`, then checks that the key of the
+/// server certificate is pinned.
+#[derive(Debug)]
+struct PinningVerifier {
+    /// `None` when no root is trusted, so no chain can be verified.
+    inner: Option<Arc<dyn ServerCertVerifier>>,
+    provider: Arc<CryptoProvider>,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        let Some(inner) = &self.inner else {
+            return Err(CertificateError::UnknownIssuer.into());
+        };
+        inner.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+        // the intermediates are whatever the server sent, verified or not
+        let pinned = spki_sha256(end_entity).is_some_and(|hash| self.pins.contains(&hash));
+        if !pinned {
+            return Err(CertificateError::ApplicationVerificationFailure.into());
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
@@ -0,0 +1,263 @@
+use async_compression::tokio::bufread::GzipEncoder;
+use http_course_core::{HttpConnection, HttpError, HttpRequest};
+use std::io::{self, Cursor};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, Join, Sink};
+use url::Url;
+
+/// The response to the second request of each test.
+const NEXT: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nnext";
+
+/// A connection to a server that answers with `raw`, whatever the requests.
+fn connection(raw: Vec<u8>) -> HttpConnection<Join<Cursor<Vec<u8>>, Sink>> {
+    HttpConnection::from_stream(tokio::io::join(Cursor::new(raw), tokio::io::sink()))
+}
+
+fn request() -> HttpRequest {
+    HttpRequest::get(Url::parse("http://localhost/").unwrap())
+}
+
+/// Reads `body` to the end, three bytes at a time.
+async fn read_small<R: AsyncRead + Unpin>(body: &mut R) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buf = [0; 3];
+    loop {
+        let read = body.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+        bytes.extend_from_slice(&buf[..read]);
+    }
+}
+
+async fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    GzipEncoder::new(bytes)
+        .read_to_end(&mut compressed)
+        .await
+        .unwrap();
+    compressed
+}
+
+/// Answers the next request on `connection`, which must be reusable.
+async fn assert_next_response<S>(connection: &mut HttpConnection<S>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let response = connection.send(request()).await.unwrap();
+    assert_eq!(response.body, b"next");
+}
+
+#[tokio::test]
+async fn reads_a_content_length_body() {
+    let mut raw = b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world".to_vec();
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    assert_eq!(
+        read_small(&mut response.body).await.unwrap(),
+        b"hello world"
+    );
+    assert!(response.body.is_done());
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn reads_a_chunked_body_with_extensions_and_trailers() {
+    let mut raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+        4;part=1\r\nwiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\n"
+        .to_vec();
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    assert_eq!(read_small(&mut response.body).await.unwrap(), b"wikipedia");
+    assert!(response.body.is_done());
+    assert_eq!(response.body.trailers().get("Expires").unwrap(), "never");
+    let extension = &response.body.chunk_extensions()[0];
+    assert_eq!(extension.chunk, 0);
+    assert_eq!(extension.name, "part");
+    assert_eq!(extension.value.as_deref(), Some("1"));
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn decodes_a_gzip_body() {
+    let text = "hello gzip, ".repeat(100);
+    let compressed = gzip(text.as_bytes()).await;
+    let mut raw = format!(
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+        compressed.len()
+    )
+    .into_bytes();
+    raw.extend_from_slice(&compressed);
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    assert_eq!(
+        read_small(&mut response.body).await.unwrap(),
+        text.as_bytes()
+    );
+    assert!(response.body.is_done());
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn skips_what_follows_the_gzip_stream() {
+    // the chunked framing of the body goes on after the end of the gzip
+    // stream, and some servers pad the body too
+    let mut body = gzip(b"compressed").await;
+    body.extend_from_slice(b"padding");
+    let mut raw =
+        b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for chunk in body.chunks(5) {
+        raw.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+        raw.extend_from_slice(chunk);
+        raw.extend_from_slice(b"\r\n");
+    }
+    raw.extend_from_slice(b"0\r\n\r\n");
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    assert_eq!(read_small(&mut response.body).await.unwrap(), b"compressed");
+    assert!(response.body.is_done());
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn reports_a_corrupt_gzip_body() {
+    let raw =
+        b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 9\r\n\r\nnot gzip!".to_vec();
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    let error = HttpError::from(read_small(&mut response.body).await.unwrap_err());
+    assert!(matches!(error, HttpError::Decompression(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn reports_a_body_shorter_than_its_length() {
+    let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello".to_vec();
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    let error = HttpError::from(read_small(&mut response.body).await.unwrap_err());
+    assert!(
+        matches!(
+            error,
+            HttpError::TruncatedBody {
+                expected: 10,
+                received: 5
+            }
+        ),
+        "{error:?}"
+    );
+}
+
+#[tokio::test]
+async fn reads_until_close_and_closes() {
+    let raw = b"HTTP/1.1 200 OK\r\n\r\nuntil the end".to_vec();
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    assert_eq!(
+        read_small(&mut response.body).await.unwrap(),
+        b"until the end"
+    );
+    assert!(response.body.is_done());
+    let error = connection.send(request()).await.unwrap_err();
+    assert!(matches!(error, HttpError::ConnectionClosed), "{error:?}");
+}
+
+#[tokio::test]
+async fn refuses_a_request_before_the_body_is_read() {
+    let mut raw = b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world".to_vec();
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let mut response = connection.send_streaming(request()).await.unwrap();
+    let mut start = [0; 5];
+    response.body.read_exact(&mut start).await.unwrap();
+    assert!(!response.body.is_done());
+    drop(response);
+    let error = connection.send(request()).await.unwrap_err();
+    assert!(matches!(error, HttpError::ConnectionNotIdle), "{error:?}");
+}
+
+#[tokio::test]
+async fn skips_interim_responses() {
+    let mut raw = b"HTTP/1.1 100 Continue\r\n\r\n\
+        HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n\
+        HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfinal"
+        .to_vec();
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let response = connection.send(request()).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert!(!response.headers.contains("Link"));
+    assert_eq!(response.body, b"final");
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn reads_no_body_for_204_and_304() {
+    for head in [
+        "HTTP/1.1 204 No Content\r\n\r\n",
+        // the length is the one of the body the server did not send
+        "HTTP/1.1 304 Not Modified\r\nContent-Length: 120\r\n\r\n",
+    ] {
+        let mut raw = head.as_bytes().to_vec();
+        raw.extend_from_slice(NEXT);
+        let mut connection = connection(raw);
+
+        let response = connection.send(request()).await.unwrap();
+        assert!(response.body.is_empty(), "{head}");
+        assert_next_response(&mut connection).await;
+    }
+}
+
+#[tokio::test]
+async fn reads_no_body_for_a_gzip_304() {
+    let mut raw =
+        b"HTTP/1.1 304 Not Modified\r\nContent-Encoding: gzip\r\nContent-Length: 120\r\n\r\n"
+            .to_vec();
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let response = connection.send(request()).await.unwrap();
+    assert_eq!(response.status, 304);
+    assert!(response.body.is_empty());
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn reads_no_body_for_a_gzip_head_response() {
+    let mut raw =
+        b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 120\r\n\r\n".to_vec();
+    raw.extend_from_slice(NEXT);
+    let mut connection = connection(raw);
+
+    let head = HttpRequest::head(Url::parse("http://localhost/").unwrap());
+    let response = connection.send(head).await.unwrap();
+    assert!(response.body.is_empty());
+    assert_next_response(&mut connection).await;
+}
+
+#[tokio::test]
+async fn reads_an_empty_gzip_body() {
+    for head in [
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: 0\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+    ] {
+        let mut raw = head.as_bytes().to_vec();
+        raw.extend_from_slice(NEXT);
+        let mut connection = connection(raw);
+
+        let response = connection.send(request()).await.unwrap();
+        assert!(response.body.is_empty(), "{head}");
+        assert_next_response(&mut connection).await;
+    }
+}
@@ -0,0 +1,293 @@
+use http_course_core::{HttpConnection, HttpError, HttpRequest, HttpResponse, Result, http10};
+use tokio::io::AsyncReadExt;
+use url::Url;
+
+/// Sends a GET request over an in-memory stream that answers with `raw`.
+async fn send_raw(raw: &[u8]) -> Result<HttpResponse> {
+    let stream = tokio::io::join(raw, tokio::io::sink());
+    let mut connection = HttpConnection::from_stream(stream);
+    let request = HttpRequest::get(Url::parse("http://localhost/").unwrap());
+    connection.send(request).await
+}
+
+fn chunked(body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+        body
+    )
+    .into_bytes()
+}
+
+#[tokio::test]
+async fn decodes_valid_chunked_body() {
+    let response = send_raw(&chunked("5\r\nhello\r\n7\r\n, world\r\n0\r\n\r\n"))
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"hello, world");
+}
+
+#[tokio::test]
+async fn accepts_uppercase_and_lowercase_hex_sizes() {
+    let body = format!(
+        "A\r\n{}\r\na\r\n{}\r\n0\r\n\r\n",
+        "x".repeat(10),
+        "y".repeat(10)
+    );
+    let response = send_raw(&chunked(&body)).await.unwrap();
+    assert_eq!(response.body.len(), 20);
+}
+
+#[tokio::test]
+async fn rejects_missing_carriage_return_after_chunk_data() {
+    let error = send_raw(&chunked("5\r\nhello\n0\r\n\r\n"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, HttpError::InvalidChunkedEncoding(_)),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn rejects_missing_new_line_after_chunk_data() {
+    let error = send_raw(&chunked("5\r\nhello\rX0\r\n\r\n"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, HttpError::InvalidChunkedEncoding(_)),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn rejects_chunk_longer_than_its_size() {
+    let error = send_raw(&chunked("3\r\nhello\r\n0\r\n\r\n"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, HttpError::InvalidChunkedEncoding(_)),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn rejects_size_line_without_carriage_return() {
+    let error = send_raw(&chunked("5\nhello\r\n0\r\n\r\n"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, HttpError::InvalidChunkedEncoding(_)),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn rejects_non_hex_chunk_size() {
+    for size in ["zz", "+5", "-5", " 5", "0x5", ""] {
+        let body = format!("{}\r\nhello\r\n0\r\n\r\n", size);
+        let error = send_raw(&chunked(&body)).await.unwrap_err();
+        assert!(
+            matches!(error, HttpError::BadChunkSize(_)),
+            "{size:?}: {error}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn rejects_overflowing_chunk_size() {
+    let error = send_raw(&chunked("1ffffffffffffffff\r\nhello\r\n0\r\n\r\n"))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, HttpError::BadChunkSize(_)), "{error}");
+}
+
+#[tokio::test]
+async fn rejects_oversized_chunk_size_line() {
+    let body = format!("{}5\r\nhello\r\n0\r\n\r\n", "0".repeat(4096));
+    let error = send_raw(&chunked(&body)).await.unwrap_err();
+    assert!(matches!(error, HttpError::BadChunkSize(_)), "{error}");
+}
+
+#[tokio::test]
+async fn rejects_truncated_chunk_data() {
+    let error = send_raw(&chunked("a\r\nhello")).await.unwrap_err();
+    assert!(
+        matches!(
+            error,
+            HttpError::TruncatedBody {
+                expected: 10,
+                received: 5
+            }
+        ),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn rejects_stream_ending_before_last_chunk() {
+    let error = send_raw(&chunked("5\r\nhello\r\n")).await.unwrap_err();
+    assert!(
+        matches!(error, HttpError::InvalidChunkedEncoding(_)),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn parses_chunk_extensions() {
+    let body = "5;name=value\r\nhello\r\n1 ; flag ;q=\"a;\\\"b\"\r\n!\r\n0;last\r\n\r\n";
+    let response = send_raw(&chunked(body)).await.unwrap();
+    assert_eq!(response.body, b"hello!");
+    let extensions: Vec<_> = response
+        .chunk_extensions
+        .iter()
+        .map(|extension| {
+            (
+                extension.chunk,
+                extension.name.as_str(),
+                extension.value.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        extensions,
+        [
+            (0, "name", Some("value")),
+            (1, "flag", None),
+            (1, "q", Some("a;\"b")),
+            (2, "last", None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rejects_malformed_chunk_extensions() {
+    for extension in [
+        ";",
+        ";=value",
+        ";name=",
+        ";name=\"unterminated",
+        ";name value",
+    ] {
+        let body = format!("5{}\r\nhello\r\n0\r\n\r\n", extension);
+        let error = send_raw(&chunked(&body)).await.unwrap_err();
+        assert!(
+            matches!(error, HttpError::InvalidChunkedEncoding(_)),
+            "{extension:?}: {error}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn parses_trailer_fields() {
+    let body = "5\r\nhello\r\n0\r\nServer-Timing: total;dur=12\r\nDigest: sha-256=abc\r\n\r\n";
+    let response = send_raw(&chunked(body)).await.unwrap();
+    assert_eq!(response.body, b"hello");
+    assert_eq!(
+        response.trailers.get("server-timing").unwrap(),
+        "total;dur=12"
+    );
+    assert_eq!(response.trailers.get("Digest").unwrap(), "sha-256=abc");
+}
+
+#[tokio::test]
+async fn rejects_unterminated_trailer_section() {
+    let body = "5\r\nhello\r\n0\r\nServer-Timing: total;dur=12\r\n";
+    let error = send_raw(&chunked(body)).await.unwrap_err();
+    assert!(
+        matches!(error, HttpError::InvalidChunkedEncoding(_)),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn rejects_head_ending_before_blank_line() {
+    let raw = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n";
+    let error = send_raw(raw).await.unwrap_err();
+    assert!(matches!(error, HttpError::TruncatedHead), "{error}");
+
+    let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 5";
+    let error = send_raw(raw).await.unwrap_err();
+    assert!(matches!(error, HttpError::TruncatedHead), "{error}");
+}
+
+#[tokio::test]
+async fn rejects_http10_head_ending_before_blank_line() {
+    let mut raw: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n";
+    let error = http10::read_response(&mut raw).await.unwrap_err();
+    assert!(matches!(error, HttpError::TruncatedHead), "{error}");
+}
+
+#[tokio::test]
+async fn decodes_header_values_lossily() {
+    let raw = b"HTTP/1.1 200 OK\r\nX-Name: caf\xe9\r\nContent-Length: 2\r\n\r\nok";
+    let response = send_raw(raw).await.unwrap();
+    assert_eq!(response.headers.get("X-Name").unwrap(), "caf\u{fffd}");
+    assert_eq!(response.body, b"ok");
+}
+
+#[tokio::test]
+async fn rejects_overlong_head_lines() {
+    let raw = format!("HTTP/1.1 200 OK\r\nX-Long: {}\r\n\r\n", "a".repeat(100_000));
+    let error = send_raw(raw.as_bytes()).await.unwrap_err();
+    assert!(matches!(error, HttpError::InvalidHeader(_)), "{error}");
+
+    let raw = format!("HTTP/1.1 200 {}\r\n\r\n", "O".repeat(100_000));
+    let error = send_raw(raw.as_bytes()).await.unwrap_err();
+    assert!(matches!(error, HttpError::InvalidHeader(_)), "{error}");
+}
+
+/// Sends `request` over an in-memory stream and returns the bytes written.
+async fn sent_head(request: HttpRequest) -> String {
+    let (stream, mut server) = tokio::io::duplex(64 * 1024);
+    let (_, writer) = tokio::io::split(stream);
+    let response: &[u8] = b"HTTP/1.1 204 No Content\r\n\r\n";
+    let mut connection = HttpConnection::from_stream(tokio::io::join(response, writer));
+    connection.send(request).await.unwrap();
+    drop(connection);
+    let mut sent = String::new();
+    server.read_to_string(&mut sent).await.unwrap();
+    sent
+}
+
+#[tokio::test]
+async fn sends_te_trailers_when_requested() {
+    let url = Url::parse("http://localhost/").unwrap();
+    let sent = sent_head(HttpRequest::get(url.clone()).with_trailers()).await;
+    assert!(sent.contains("\r\nte: trailers\r\n"), "{sent}");
+    assert!(sent.contains("\r\nconnection: TE\r\n"), "{sent}");
+
+    // the Connection options of the caller are kept, before or after
+    let request = HttpRequest::get(url.clone())
+        .header("connection", "close")
+        .with_trailers();
+    let sent = sent_head(request).await;
+    assert!(sent.contains("\r\nconnection: close, TE\r\n"), "{sent}");
+
+    let request = HttpRequest::get(url)
+        .with_trailers()
+        .header("connection", "close");
+    let sent = sent_head(request).await;
+    assert!(sent.contains("\r\nconnection: close, TE\r\n"), "{sent}");
+}
+
+#[tokio::test]
+async fn rejects_whitespace_around_header_names() {
+    for raw in [
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding : chunked\r\n\r\n0\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Length\t: 0\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n Content-Length: 0\r\n\r\n",
+    ] {
+        let error = send_raw(raw.as_bytes()).await.unwrap_err();
+        assert!(matches!(error, HttpError::InvalidHeader(_)), "{error}");
+    }
+}
+
+#[tokio::test]
+async fn detects_chunked_body_with_lowercase_header() {
+    let raw = b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nset-cookie: a=1\r\nSet-Cookie: b=2\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+    let response = send_raw(raw).await.unwrap();
+    assert_eq!(response.body, b"hello");
+    let cookies: Vec<_> = response.headers.get_all("Set-Cookie").collect();
+    assert_eq!(cookies, ["a=1", "b=2"]);
+}
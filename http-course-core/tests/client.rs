@@ -0,0 +1,277 @@
+mod common;
+
+use common::{Listener, Server, ok, response, self_signed, serve};
+use http_course_core::{
+    HttpClient, HttpError, HttpMethod, HttpRequest, HttpResponse, Origin, RedirectPolicy, TlsConfig,
+};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use url::Url;
+
+async fn get(client: &HttpClient, url: Url) -> HttpResponse {
+    client.send(HttpRequest::get(url)).await.unwrap()
+}
+
+#[tokio::test]
+async fn reuses_an_idle_connection() {
+    let server = serve(|_| async { ok("hello") }).await;
+    let client = HttpClient::new();
+    let origin = Origin::from_url(&server.url).unwrap();
+
+    for _ in 0..3 {
+        let response = get(&client, server.url("/")).await;
+        assert_eq!(response.body, b"hello");
+        assert_eq!(client.idle_connections(&origin), 1);
+    }
+    assert_eq!(server.connections(), 1);
+
+    client.close_idle_connections();
+    assert_eq!(client.idle_connections(&origin), 0);
+    get(&client, server.url("/")).await;
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn caps_the_connections_per_host() {
+    let server = serve(|_| async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        ok("slow")
+    })
+    .await;
+    let client = HttpClient::new().max_connections_per_host(2);
+
+    let mut requests = JoinSet::new();
+    for _ in 0..6 {
+        let client = client.clone();
+        let url = server.url("/");
+        requests.spawn(async move { client.send(HttpRequest::get(url)).await });
+    }
+    while let Some(response) = requests.join_next().await {
+        assert_eq!(response.unwrap().unwrap().body, b"slow");
+    }
+    assert_eq!(server.stats.max_active.load(Ordering::SeqCst), 2);
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn drops_connections_past_the_keep_alive_timeout() {
+    let server =
+        serve(|_| async { Some(response("200 OK", "Keep-Alive: timeout=1\r\n", "")) }).await;
+    let client = HttpClient::new();
+
+    get(&client, server.url("/")).await;
+    get(&client, server.url("/")).await;
+    assert_eq!(server.connections(), 1);
+    // the server would close the connection about now
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    get(&client, server.url("/")).await;
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn drops_connections_past_the_idle_timeout() {
+    let server = serve(|_| async { ok("") }).await;
+    let client = HttpClient::new().idle_timeout(Duration::from_millis(100));
+
+    get(&client, server.url("/")).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    get(&client, server.url("/")).await;
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn does_not_keep_connections_the_server_closes() {
+    let server =
+        serve(|_| async { Some(response("200 OK", "Connection: close\r\n", "bye")) }).await;
+    let client = HttpClient::new();
+    let origin = Origin::from_url(&server.url).unwrap();
+
+    for _ in 0..2 {
+        let response = get(&client, server.url("/")).await;
+        assert_eq!(response.body, b"bye");
+        assert_eq!(client.idle_connections(&origin), 0);
+    }
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn does_not_keep_connections_the_request_closes() {
+    let server = serve(|_| async { ok("") }).await;
+    let client = HttpClient::new();
+    let origin = Origin::from_url(&server.url).unwrap();
+
+    let request = HttpRequest::get(server.url("/")).header("Connection", "close");
+    client.send(request).await.unwrap();
+    assert_eq!(client.idle_connections(&origin), 0);
+    get(&client, server.url("/")).await;
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn replays_an_idempotent_request_on_a_stale_connection() {
+    // the server closes the connection on its second request, like one
+    // that timed it out just as the request was sent
+    let server = serve(|received| async move {
+        (received.index == 0).then(|| response("200 OK", "", &received.body))
+    })
+    .await;
+    let client = HttpClient::new();
+
+    get(&client, server.url("/")).await;
+    let request = HttpRequest::put(server.url("/")).text("again");
+    let response = client.send(request).await.unwrap();
+    assert_eq!(response.body, b"again");
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn does_not_replay_a_post_on_a_stale_connection() {
+    let server =
+        serve(|received| async move { (received.index == 0).then(|| response("200 OK", "", "")) })
+            .await;
+    let client = HttpClient::new();
+
+    get(&client, server.url("/")).await;
+    let request = HttpRequest::post(server.url("/")).text("once");
+    let error = client.send(request).await.unwrap_err();
+    assert!(
+        matches!(error, HttpError::NotRetried(HttpMethod::Post)),
+        "{error:?}"
+    );
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn does_not_replay_on_a_new_connection() {
+    let server = serve(|_| async { None }).await;
+    let client = HttpClient::new();
+
+    let error = client
+        .send(HttpRequest::get(server.url("/")))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, HttpError::ConnectionClosed), "{error:?}");
+    assert_eq!(server.connections(), 1);
+}
+
+/// Redirects `/from-*` paths with the status after the dash to `location`,
+/// and describes the other requests: their request line, credentials and
+/// body.
+async fn serve_redirects(location: impl Into<String>) -> Server {
+    let location = location.into();
+    serve(move |received| {
+        let location = location.clone();
+        async move {
+            match received.path().strip_prefix("/from-") {
+                Some(status) => Some(response(
+                    &format!("{status} Redirect"),
+                    &format!("Location: {location}\r\n"),
+                    "",
+                )),
+                None => {
+                    let credentials: Vec<&str> = ["authorization", "cookie", "proxy-authorization"]
+                        .iter()
+                        .map(|name| received.header(name).unwrap_or("-"))
+                        .collect();
+                    let description = format!(
+                        "{}\n{}\n{}",
+                        received.line,
+                        credentials.join(" | "),
+                        received.body
+                    );
+                    ok(&description)
+                }
+            }
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn turns_a_post_into_a_get_after_a_303() {
+    let server = serve_redirects("/done").await;
+    let client = HttpClient::new();
+
+    let request = HttpRequest::post(server.url("/from-303")).text("payload");
+    let response = client.send(request).await.unwrap();
+    assert_eq!(response.body, b"GET /done HTTP/1.1\n- | - | -\n");
+    assert_eq!(response.redirects, [server.url("/done")]);
+}
+
+#[tokio::test]
+async fn keeps_the_method_and_body_after_a_307() {
+    let server = serve_redirects("/done").await;
+    let client = HttpClient::new();
+
+    let request = HttpRequest::post(server.url("/from-307")).text("payload");
+    let response = client.send(request).await.unwrap();
+    assert_eq!(response.body, b"POST /done HTTP/1.1\n- | - | -\npayload");
+}
+
+#[tokio::test]
+async fn sends_the_authorization_to_the_same_origin_only() {
+    let other = serve_redirects("/").await;
+    let server = serve_redirects(other.url("/elsewhere")).await;
+    let same = serve_redirects("/here").await;
+    let client = HttpClient::new();
+
+    let credentials = |request: HttpRequest| {
+        request
+            .header("Authorization", "Bearer t")
+            .header("Cookie", "sid=1")
+            .header("Proxy-Authorization", "Basic cDpx")
+    };
+    let request = credentials(HttpRequest::get(same.url("/from-302")));
+    let response = client.send(request).await.unwrap();
+    assert_eq!(
+        response.body,
+        b"GET /here HTTP/1.1\nBearer t | sid=1 | Basic cDpx\n"
+    );
+
+    let request = credentials(HttpRequest::get(server.url("/from-302")));
+    let response = client.send(request).await.unwrap();
+    assert_eq!(response.body, b"GET /elsewhere HTTP/1.1\n- | - | -\n");
+}
+
+#[tokio::test]
+async fn refuses_to_follow_https_to_http() {
+    let plain = serve_redirects("/").await;
+    let location = plain.url("/plain").to_string();
+
+    // an HTTPS server that redirects every request to the plain one
+    let (cert, config) = self_signed(&["localhost"]);
+    let server = Listener::tls(config).await.serve(move |_| {
+        let extra = format!("Location: {location}\r\n");
+        async move { Some(response("302 Found", &extra, "")) }
+    });
+    let tls = TlsConfig::new()
+        .webpki_roots(false)
+        .add_ca_certificate(cert);
+    let url = server.url("/");
+
+    let client = HttpClient::new().tls(tls.clone());
+    let response = client.send(HttpRequest::get(url.clone())).await.unwrap();
+    assert_eq!(response.status, 302);
+    assert!(response.redirects.is_empty());
+    assert_eq!(plain.connections(), 0);
+
+    let client = HttpClient::new()
+        .tls(tls)
+        .redirect_policy(RedirectPolicy::new().allow_downgrade(true));
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(plain.connections(), 1);
+}
+
+#[tokio::test]
+async fn gives_up_on_a_redirect_loop() {
+    let server = serve_redirects("/from-302").await;
+    let client = HttpClient::new().redirect_policy(RedirectPolicy::new().max_redirects(3));
+
+    let error = client
+        .send(HttpRequest::get(server.url("/from-302")))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, HttpError::TooManyRedirects(3)), "{error:?}");
+}
@@ -0,0 +1,410 @@
+//! Fixtures shared by the integration tests: HTTP/1.1 servers on loopback
+//! that answer the requests they receive with a callback, and the
+//! certificates for their TLS variant.
+
+// every test binary uses a part of the fixtures only
+#![allow(dead_code)]
+
+use http_course_core::{ClientIdentity, TlsConfig};
+use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair};
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use url::Url;
+
+/// A request as a test server received it.
+#[derive(Debug)]
+pub struct Received {
+    /// The request line.
+    pub line: String,
+    /// The header lines, without their CRLF.
+    pub headers: Vec<String>,
+    pub body: String,
+    /// The number of requests received before on the same connection.
+    pub index: usize,
+    /// The number of requests received before by the server.
+    pub sequence: usize,
+}
+
+impl Received {
+    pub fn path(&self) -> &str {
+        self.line.split(' ').nth(1).unwrap_or_default()
+    }
+
+    /// The value of the first header `name`, whatever its case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find_map(|line| {
+            let (header, value) = line.split_once(':')?;
+            header.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// The request line and the header lines, joined with `\n`.
+    pub fn head(&self) -> String {
+        let mut head = self.line.clone();
+        for header in &self.headers {
+            head.push('\n');
+            head.push_str(header);
+        }
+        head
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub connections: AtomicUsize,
+    pub active: AtomicUsize,
+    pub max_active: AtomicUsize,
+    pub requests: AtomicUsize,
+}
+
+/// A running test server.
+pub struct Server {
+    pub url: Url,
+    pub stats: Arc<Stats>,
+}
+
+impl Server {
+    pub fn url(&self, path: &str) -> Url {
+        self.url.join(path).unwrap()
+    }
+
+    pub fn port(&self) -> u16 {
+        self.url.port().unwrap()
+    }
+
+    /// The address of a server listening on an IP address.
+    pub fn address(&self) -> SocketAddr {
+        self.url.socket_addrs(|| None).unwrap()[0]
+    }
+
+    /// The number of connections accepted.
+    pub fn connections(&self) -> usize {
+        self.stats.connections.load(Ordering::SeqCst)
+    }
+
+    /// The number of requests read.
+    pub fn requests(&self) -> usize {
+        self.stats.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// A stream a test server answers on.
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Stream for S {}
+
+/// The connection a request was received on, to answer it.
+pub type Connection = BufReader<Box<dyn Stream>>;
+
+/// Where a test server listens.
+pub enum Listener {
+    Tcp(TcpListener),
+    Tls(TcpListener, TlsAcceptor),
+    #[cfg(unix)]
+    Unix(UnixListener, Url),
+}
+
+impl Listener {
+    /// Listens on a free port of `127.0.0.1`.
+    pub async fn tcp() -> Self {
+        Self::tcp_on("127.0.0.1:0".parse().unwrap()).await.unwrap()
+    }
+
+    /// Listens on `address`, or returns `None` when its address family is
+    /// not available.
+    pub async fn tcp_on(address: SocketAddr) -> Option<Self> {
+        TcpListener::bind(address).await.ok().map(Self::Tcp)
+    }
+
+    /// Listens on a free port of `127.0.0.1` and runs the TLS handshake
+    /// with `config` on each connection. The url names `localhost`.
+    pub async fn tls(config: ServerConfig) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        Self::Tls(listener, TlsAcceptor::from(Arc::new(config)))
+    }
+
+    /// Listens on a Unix socket at `path`, replacing any previous one.
+    #[cfg(unix)]
+    pub fn unix(path: &Path) -> Self {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).unwrap();
+        let host = path.to_str().unwrap().replace('/', "%2F");
+        let url = Url::parse(&format!("http+unix://{host}/")).unwrap();
+        Self::Unix(listener, url)
+    }
+
+    fn url(&self) -> Url {
+        match self {
+            Self::Tcp(listener) => {
+                let address = listener.local_addr().unwrap();
+                Url::parse(&format!("http://{address}/")).unwrap()
+            }
+            Self::Tls(listener, _) => {
+                let port = listener.local_addr().unwrap().port();
+                Url::parse(&format!("https://localhost:{port}/")).unwrap()
+            }
+            #[cfg(unix)]
+            Self::Unix(_, url) => url.clone(),
+        }
+    }
+
+    async fn accept(&self) -> Box<dyn Stream> {
+        match self {
+            Self::Tcp(listener) | Self::Tls(listener, _) => {
+                Box::new(listener.accept().await.unwrap().0)
+            }
+            #[cfg(unix)]
+            Self::Unix(listener, _) => Box::new(listener.accept().await.unwrap().0),
+        }
+    }
+
+    fn acceptor(&self) -> Option<TlsAcceptor> {
+        match self {
+            Self::Tls(_, acceptor) => Some(acceptor.clone()),
+            _ => None,
+        }
+    }
+
+    /// Answers each request with what `respond` returns, or closes the
+    /// connection without answering on `None`. The connection is closed
+    /// after a response with `Connection: close` too.
+    pub fn serve<F, Fut>(self, respond: F) -> Server
+    where
+        F: Fn(Received) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<String>> + Send,
+    {
+        let respond = Arc::new(respond);
+        self.serve_with(move |received, mut connection| {
+            let respond = respond.clone();
+            async move {
+                let response = respond(received).await?;
+                connection.write_all(response.as_bytes()).await.ok()?;
+                let close = response.to_ascii_lowercase().contains("connection: close");
+                (!close).then_some(connection)
+            }
+        })
+    }
+
+    /// Hands each request and its connection to `respond`, which writes the
+    /// response itself and gives the connection back to keep it open.
+    /// Connections are counted before the TLS handshake, and dropped when it
+    /// fails.
+    pub fn serve_with<F, Fut>(self, respond: F) -> Server
+    where
+        F: Fn(Received, Connection) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<Connection>> + Send,
+    {
+        let url = self.url();
+        let stats = Arc::new(Stats::default());
+        let counted = stats.clone();
+        let respond = Arc::new(respond);
+        tokio::spawn(async move {
+            loop {
+                let stream = self.accept().await;
+                let acceptor = self.acceptor();
+                let stats = counted.clone();
+                let respond = respond.clone();
+                stats.connections.fetch_add(1, Ordering::SeqCst);
+                let active = stats.active.fetch_add(1, Ordering::SeqCst) + 1;
+                stats.max_active.fetch_max(active, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    if let Some(stream) = handshake(acceptor, stream).await {
+                        let mut connection = BufReader::new(stream);
+                        let mut index = 0;
+                        while let Some(mut received) = read_request(&mut connection, index).await {
+                            received.sequence = stats.requests.fetch_add(1, Ordering::SeqCst);
+                            match respond(received, connection).await {
+                                Some(next) => connection = next,
+                                None => break,
+                            }
+                            index += 1;
+                        }
+                    }
+                    stats.active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Server { url, stats }
+    }
+}
+
+/// Runs the TLS handshake on `stream` when there is an `acceptor`.
+async fn handshake(
+    acceptor: Option<TlsAcceptor>,
+    stream: Box<dyn Stream>,
+) -> Option<Box<dyn Stream>> {
+    match acceptor {
+        Some(acceptor) => Some(Box::new(acceptor.accept(stream).await.ok()?)),
+        None => Some(stream),
+    }
+}
+
+/// Starts a server on a free port of `127.0.0.1` that answers each request
+/// with what `respond` returns, as [`Listener::serve`] does.
+pub async fn serve<F, Fut>(respond: F) -> Server
+where
+    F: Fn(Received) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Option<String>> + Send,
+{
+    Listener::tcp().await.serve(respond)
+}
+
+/// Reads a request and its `Content-Length` body, or returns `None` at the
+/// end of the stream.
+pub async fn read_request<S>(stream: &mut BufReader<S>, index: usize) -> Option<Received>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        if stream.read_line(&mut header).await.ok()? == 0 {
+            return None;
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+        headers.push(header.trim_end().to_string());
+    }
+    let mut received = Received {
+        line: line.trim_end().to_string(),
+        headers,
+        body: String::new(),
+        index,
+        sequence: 0,
+    };
+    let length = received.header("content-length").map_or(Ok(0), str::parse);
+    let mut body = vec![0; length.ok()?];
+    stream.read_exact(&mut body).await.ok()?;
+    received.body = String::from_utf8(body).ok()?;
+    Some(received)
+}
+
+/// A response with `extra` header lines, each ending with CRLF.
+pub fn response(status: &str, extra: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n{extra}Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+pub fn ok(body: &str) -> Option<String> {
+    Some(response("200 OK", "", body))
+}
+
+/// A CA with a server certificate for `localhost` and a client certificate.
+pub struct Pki {
+    pub ca: CertificateDer<'static>,
+    pub ca_pem: String,
+    pub server_cert: CertificateDer<'static>,
+    pub server_key: KeyPair,
+    pub client_cert: rcgen::Certificate,
+    pub client_key: KeyPair,
+}
+
+impl Pki {
+    pub fn new() -> Self {
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate().unwrap();
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let issuer = Issuer::from_params(&ca_params, &ca_key);
+
+        let server_key = KeyPair::generate().unwrap();
+        let server_cert = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&server_key, &issuer)
+            .unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(vec!["client".to_string()]).unwrap();
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client_cert = client_params.signed_by(&client_key, &issuer).unwrap();
+
+        Self {
+            ca: ca.der().clone(),
+            ca_pem: ca.pem(),
+            server_cert: server_cert.der().clone(),
+            server_key,
+            client_cert,
+            client_key,
+        }
+    }
+
+    pub fn identity(&self) -> ClientIdentity {
+        ClientIdentity::from_pem(
+            self.client_cert.pem().as_bytes(),
+            self.client_key.serialize_pem().as_bytes(),
+        )
+        .unwrap()
+    }
+
+    /// Trusts the CA of the server, and nothing else.
+    pub fn tls_config(&self) -> TlsConfig {
+        TlsConfig::new()
+            .webpki_roots(false)
+            .add_ca_certificate(self.ca.clone())
+    }
+
+    /// A server that presents `chain` with the server key.
+    pub fn server_config(&self, chain: Vec<CertificateDer<'static>>) -> ServerConfig {
+        ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(chain, private_key(&self.server_key))
+            .unwrap()
+    }
+
+    /// A server that presents its certificate, and requires a client
+    /// certificate signed by the CA.
+    pub fn client_auth_server_config(&self) -> ServerConfig {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca.clone()).unwrap();
+        let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+            .build()
+            .unwrap();
+        ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                vec![self.server_cert.clone()],
+                private_key(&self.server_key),
+            )
+            .unwrap()
+    }
+}
+
+/// A self-signed certificate for `names`, and a server that presents it.
+pub fn self_signed(names: &[&str]) -> (CertificateDer<'static>, ServerConfig) {
+    let key = KeyPair::generate().unwrap();
+    let names = names
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    let cert = CertificateParams::new(names)
+        .unwrap()
+        .self_signed(&key)
+        .unwrap();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.der().clone()], private_key(&key))
+        .unwrap();
+    (cert.der().clone(), config)
+}
+
+fn private_key(key: &KeyPair) -> PrivateKeyDer<'static> {
+    PrivateKeyDer::from(PrivatePkcs8KeyDer::from(key.serialize_der()))
+}
@@ -0,0 +1,218 @@
+mod common;
+
+use common::{response, serve};
+use http_course_core::{Cookie, CookieJar, HeaderMap, HttpClient, HttpRequest};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+fn url(url: &str) -> Url {
+    Url::parse(url).unwrap()
+}
+
+fn store(jar: &CookieJar, from: &str, set_cookie: &str) {
+    let mut headers = HeaderMap::new();
+    headers.append("Set-Cookie", set_cookie);
+    jar.store(&url(from), &headers);
+}
+
+#[test]
+fn refuses_a_top_level_domain() {
+    let jar = CookieJar::new();
+    store(&jar, "https://attacker.com/", "sid=evil; Domain=com");
+    store(&jar, "https://attacker.com/", "sid=evil; Domain=.COM");
+    assert_eq!(jar.cookie_header(&url("https://bank.com/")), None);
+    assert!(jar.cookies().is_empty());
+}
+
+#[test]
+fn keeps_a_single_label_host_to_itself() {
+    let cookie = Cookie::parse("sid=1; Domain=localhost", &url("http://localhost/")).unwrap();
+    assert!(cookie.host_only);
+    assert!(cookie.matches(&url("http://localhost/")));
+    assert!(!cookie.matches(&url("http://app.localhost/")));
+}
+
+#[test]
+fn matches_the_subdomains_of_the_domain_attribute() {
+    let from = url("https://www.example.com/");
+    let cookie = Cookie::parse("a=1; Domain=example.com", &from).unwrap();
+    assert!(!cookie.host_only);
+    assert!(cookie.matches(&url("https://example.com/")));
+    assert!(cookie.matches(&url("https://api.example.com/")));
+    assert!(!cookie.matches(&url("https://badexample.com/")));
+
+    let cookie = Cookie::parse("b=2", &from).unwrap();
+    assert!(cookie.host_only);
+    assert!(cookie.matches(&url("https://www.example.com/")));
+    assert!(!cookie.matches(&url("https://api.www.example.com/")));
+
+    // a sibling domain is not the server's to set
+    assert_eq!(Cookie::parse("c=3; Domain=other.com", &from), None);
+    assert_eq!(Cookie::parse("c=3; Domain=api.example.com", &from), None);
+}
+
+#[test]
+fn matches_the_path_and_below() {
+    let from = url("https://example.com/docs/index.html");
+    let cookie = Cookie::parse("a=1", &from).unwrap();
+    assert_eq!(cookie.path, "/docs");
+    assert!(cookie.matches(&url("https://example.com/docs")));
+    assert!(cookie.matches(&url("https://example.com/docs/api")));
+    assert!(!cookie.matches(&url("https://example.com/docsearch")));
+    assert!(!cookie.matches(&url("https://example.com/")));
+
+    let cookie = Cookie::parse("b=2; Path=/", &from).unwrap();
+    assert!(cookie.matches(&url("https://example.com/anything")));
+}
+
+#[test]
+fn sends_secure_cookies_over_https_only() {
+    let cookie = Cookie::parse("a=1; Secure", &url("https://example.com/")).unwrap();
+    assert!(cookie.matches(&url("https://example.com/")));
+    assert!(!cookie.matches(&url("http://example.com/")));
+}
+
+#[test]
+fn prefers_max_age_over_expires() {
+    let from = url("https://example.com/");
+    let cookie = Cookie::parse("a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT", &from).unwrap();
+    assert_eq!(
+        cookie.expires,
+        Some(UNIX_EPOCH + Duration::from_secs(1445412480))
+    );
+    assert!(cookie.is_expired());
+
+    let cookie = Cookie::parse(
+        "a=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=3600",
+        &from,
+    )
+    .unwrap();
+    let expires = cookie.expires.unwrap();
+    assert!(expires > SystemTime::now() + Duration::from_secs(3500));
+    assert!(!cookie.is_expired());
+
+    let cookie = Cookie::parse("a=1; Max-Age=0", &from).unwrap();
+    assert!(cookie.is_expired());
+    assert_eq!(Cookie::parse("a=1", &from).unwrap().expires, None);
+}
+
+#[test]
+fn removes_a_cookie_set_again_with_a_past_expiry() {
+    let jar = CookieJar::new();
+    store(&jar, "https://example.com/", "a=1");
+    store(&jar, "https://example.com/", "b=2");
+    assert_eq!(
+        jar.cookie_header(&url("https://example.com/")).as_deref(),
+        Some("a=1; b=2")
+    );
+    store(&jar, "https://example.com/", "a=1; Max-Age=-1");
+    assert_eq!(
+        jar.cookie_header(&url("https://example.com/")).as_deref(),
+        Some("b=2")
+    );
+}
+
+#[test]
+fn removes_a_cookie_set_again_with_a_dashed_expiry() {
+    let jar = CookieJar::new();
+    store(&jar, "https://example.com/", "sid=1");
+    store(
+        &jar,
+        "https://example.com/",
+        "sid=1; Expires=Thu, 01-Jan-1970 00:00:00 GMT",
+    );
+    assert_eq!(jar.cookie_header(&url("https://example.com/")), None);
+}
+
+#[test]
+fn parses_the_cookie_date_forms_of_the_wild() {
+    let from = url("https://example.com/");
+    let expected = Some(UNIX_EPOCH + Duration::from_secs(4102444800));
+    for date in [
+        "Fri, 01 Jan 2100 00:00:00 GMT",
+        "Fri, 01-Jan-2100 00:00:00 GMT",
+        "Fri, 1-jan-2100 0:00:00 UTC",
+        "Fri Jan  1 00:00:00 2100",
+        "01 january 2100 0:0:0",
+    ] {
+        let cookie = Cookie::parse(&format!("a=1; Expires={date}"), &from).unwrap();
+        assert_eq!(cookie.expires, expected, "{date}");
+    }
+    let cookie = Cookie::parse("a=1; Expires=Sunday, 06-Nov-94 08:49:37 GMT", &from).unwrap();
+    assert_eq!(
+        cookie.expires,
+        Some(UNIX_EPOCH + Duration::from_secs(784111777))
+    );
+    for date in [
+        "Fri, 31-Feb-2100 00:00:00 GMT",
+        "Fri, 01-Jan-2100 24:00:00 GMT",
+        "Fri, 01-Jan-2100",
+        "tomorrow",
+    ] {
+        let cookie = Cookie::parse(&format!("a=1; Expires={date}"), &from).unwrap();
+        assert_eq!(cookie.expires, None, "{date}");
+    }
+}
+
+#[test]
+fn sends_longer_paths_first() {
+    let jar = CookieJar::new();
+    store(&jar, "https://example.com/", "a=1; Path=/");
+    store(&jar, "https://example.com/", "b=2; Path=/docs");
+    assert_eq!(
+        jar.cookie_header(&url("https://example.com/docs/api"))
+            .as_deref(),
+        Some("b=2; a=1")
+    );
+}
+
+#[test]
+fn saves_and_loads_the_netscape_format() {
+    let jar = CookieJar::new();
+    store(&jar, "https://example.com/", "session=abc");
+    store(
+        &jar,
+        "https://www.example.com/",
+        "sid=1; Domain=example.com; Path=/app; Secure; HttpOnly; Max-Age=3600",
+    );
+    let path = std::env::temp_dir().join(format!("http-course-cookies-{}.txt", std::process::id()));
+    jar.save(&path).unwrap();
+    let file = std::fs::read_to_string(&path).unwrap();
+    let loaded = CookieJar::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(file.starts_with("# Netscape HTTP Cookie File\n"));
+    assert!(file.contains("\nexample.com\tFALSE\t/\tFALSE\t0\tsession\tabc\n"));
+    assert!(file.contains("\n#HttpOnly_.example.com\tTRUE\t/app\tTRUE\t"));
+
+    // the file keeps whole seconds
+    let truncate = |mut cookie: Cookie| {
+        cookie.expires = cookie.expires.map(|expires| {
+            let seconds = expires.duration_since(UNIX_EPOCH).unwrap().as_secs();
+            UNIX_EPOCH + Duration::from_secs(seconds)
+        });
+        cookie
+    };
+    let saved: Vec<Cookie> = jar.cookies().into_iter().map(truncate).collect();
+    assert_eq!(loaded.unwrap().cookies(), saved);
+}
+
+#[tokio::test]
+async fn sends_the_cookies_back_to_the_server() {
+    // sets a cookie, then answers with the Cookie header it receives
+    let server = serve(|received| async move {
+        let cookie = received.header("cookie").unwrap_or_default();
+        Some(response("200 OK", "Set-Cookie: visits=1\r\n", cookie))
+    })
+    .await;
+    let jar = Arc::new(CookieJar::new());
+    let client = HttpClient::new().cookie_jar(jar.clone());
+
+    let url = server.url("/");
+    let response = client.send(HttpRequest::get(url.clone())).await.unwrap();
+    assert_eq!(response.body, b"");
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.body, b"visits=1");
+    assert_eq!(jar.cookies().len(), 1);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::{Listener, ok};
+use http_course_core::{HttpClient, HttpRequest, Resolver, Resolving};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use url::Url;
+
+/// Resolves every host to the same addresses, ports included, so that the
+/// IPv6 and IPv4 servers of a test can listen on different ports.
+#[derive(Debug)]
+struct FixedResolver(Vec<SocketAddr>);
+
+impl Resolver for FixedResolver {
+    fn resolve<'a>(&'a self, _host: &'a str, _port: u16) -> Resolving<'a> {
+        Box::pin(async move { Ok(self.0.clone()) })
+    }
+}
+
+/// Starts a server on `address` that answers every request with `name`,
+/// or returns `None` when the address family is not available.
+async fn serve(address: &str, name: &'static str) -> Option<SocketAddr> {
+    let listener = Listener::tcp_on(address.parse().unwrap()).await?;
+    Some(listener.serve(move |_| async move { ok(name) }).address())
+}
+
+async fn ipv6_available() -> bool {
+    TcpListener::bind("[::1]:0").await.is_ok()
+}
+
+/// An address on `::1` where connecting hangs, like a black-holed route: the
+/// accept queue of the listener is full and nobody accepts. The address
+/// stays so while the returned guard lives.
+async fn black_hole() -> (SocketAddr, (TcpListener, TcpStream)) {
+    let socket = TcpSocket::new_v6().unwrap();
+    socket.bind("[::1]:0".parse().unwrap()).unwrap();
+    let address = socket.local_addr().unwrap();
+    let listener = socket.listen(0).unwrap();
+    let filler = TcpStream::connect(address).await.unwrap();
+    (address, (listener, filler))
+}
+
+/// The address of a closed port on `::1`, where connecting is refused.
+async fn refused() -> SocketAddr {
+    let listener = TcpListener::bind("[::1]:0").await.unwrap();
+    listener.local_addr().unwrap()
+}
+
+async fn get(client: &HttpClient) -> String {
+    let url = Url::parse("http://dual-stack.test/").unwrap();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    String::from_utf8(response.body).unwrap()
+}
+
+#[tokio::test]
+async fn prefers_ipv6_when_both_connect() {
+    let Some(ipv6) = serve("[::1]:0", "ipv6").await else {
+        eprintln!("skipped: IPv6 is not available");
+        return;
+    };
+    let ipv4 = serve("127.0.0.1:0", "ipv4").await.unwrap();
+
+    let client = HttpClient::new().resolver(FixedResolver(vec![ipv6, ipv4]));
+    assert_eq!(get(&client).await, "ipv6");
+}
+
+#[tokio::test]
+async fn falls_back_at_once_when_ipv6_is_refused() {
+    if !ipv6_available().await {
+        eprintln!("skipped: IPv6 is not available");
+        return;
+    }
+    let ipv6 = refused().await;
+    let ipv4 = serve("127.0.0.1:0", "ipv4").await.unwrap();
+
+    let client = HttpClient::new()
+        .resolver(FixedResolver(vec![ipv6, ipv4]))
+        .happy_eyeballs_delay(Duration::from_secs(10));
+    let started = Instant::now();
+    assert_eq!(get(&client).await, "ipv4");
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn falls_back_after_the_delay_when_ipv6_hangs() {
+    if !ipv6_available().await {
+        eprintln!("skipped: IPv6 is not available");
+        return;
+    }
+    let (ipv6, _guard) = black_hole().await;
+    let ipv4 = serve("127.0.0.1:0", "ipv4").await.unwrap();
+
+    let client = HttpClient::new()
+        .resolver(FixedResolver(vec![ipv6, ipv4]))
+        .happy_eyeballs_delay(Duration::from_millis(100))
+        .connect_timeout(Duration::from_secs(5));
+    let started = Instant::now();
+    assert_eq!(get(&client).await, "ipv4");
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn alternates_the_address_families() {
+    if !ipv6_available().await {
+        eprintln!("skipped: IPv6 is not available");
+        return;
+    }
+    let (first, _first_guard) = black_hole().await;
+    let (second, _second_guard) = black_hole().await;
+    let ipv4 = serve("127.0.0.1:0", "ipv4").await.unwrap();
+
+    // the IPv4 address comes second, so it is tried after one delay and
+    // not two, which would be past the connect timeout
+    let client = HttpClient::new()
+        .resolver(FixedResolver(vec![first, second, ipv4]))
+        .happy_eyeballs_delay(Duration::from_millis(300))
+        .connect_timeout(Duration::from_millis(500));
+    assert_eq!(get(&client).await, "ipv4");
+}
@@ -0,0 +1,96 @@
+use http_course_core::{HeaderMap, HttpError};
+
+fn pairs(headers: &HeaderMap) -> Vec<(&str, &str)> {
+    headers.iter().collect()
+}
+
+#[test]
+fn looks_names_up_ignoring_case() {
+    let headers = HeaderMap::from_iter([("Content-Type", "text/html")]);
+    assert_eq!(headers.get("content-type"), Some("text/html"));
+    assert_eq!(headers.get("CONTENT-TYPE"), Some("text/html"));
+    assert!(headers.contains("Content-type"));
+    assert_eq!(headers.get("Content-Length"), None);
+}
+
+#[test]
+fn inserts_at_the_first_position_and_drops_the_duplicates() {
+    let mut headers = HeaderMap::from_iter([
+        ("Accept", "text/html"),
+        ("Host", "example.com"),
+        ("accept", "text/plain"),
+        ("User-Agent", "course"),
+        ("ACCEPT", "*/*"),
+    ]);
+    headers.insert("accept", "application/json");
+    assert_eq!(
+        pairs(&headers),
+        [
+            ("accept", "application/json"),
+            ("Host", "example.com"),
+            ("User-Agent", "course"),
+        ]
+    );
+
+    headers.insert("Connection", "close");
+    assert_eq!(headers.iter().last(), Some(("Connection", "close")));
+    assert_eq!(headers.len(), 4);
+}
+
+#[test]
+fn appends_and_gets_all_the_values_in_order() {
+    let mut headers = HeaderMap::new();
+    headers.append("Set-Cookie", "a=1");
+    headers.append("Vary", "Accept");
+    headers.append("set-cookie", "b=2");
+    headers.append("SET-COOKIE", "c=3");
+    let cookies: Vec<&str> = headers.get_all("Set-Cookie").collect();
+    assert_eq!(cookies, ["a=1", "b=2", "c=3"]);
+    assert_eq!(headers.get("set-cookie"), Some("a=1"));
+
+    assert_eq!(headers.remove("Set-Cookie"), ["a=1", "b=2", "c=3"]);
+    assert_eq!(pairs(&headers), [("Vary", "Accept")]);
+}
+
+#[test]
+fn splits_list_fields_across_values() {
+    let headers = HeaderMap::from_iter([
+        ("Connection", "keep-alive, Upgrade"),
+        ("Accept", "text/html"),
+        ("connection", " ,close ,"),
+    ]);
+    let tokens: Vec<&str> = headers.get_list("Connection").collect();
+    assert_eq!(tokens, ["keep-alive", "Upgrade", "close"]);
+    assert!(headers.contains_token("connection", "CLOSE"));
+    assert!(headers.contains_token("Connection", "upgrade"));
+    assert!(!headers.contains_token("Connection", "keep"));
+    assert!(!headers.contains_token("Accept", "close"));
+}
+
+#[test]
+fn reads_the_content_length() {
+    let length = |values: &[&str]| {
+        let headers: HeaderMap = values
+            .iter()
+            .map(|value| ("Content-Length", *value))
+            .collect();
+        headers.content_length()
+    };
+    assert_eq!(length(&[]).unwrap(), None);
+    assert_eq!(length(&["42"]).unwrap(), Some(42));
+    // repeated values are fine when they agree
+    assert_eq!(length(&["42", "42"]).unwrap(), Some(42));
+    assert_eq!(length(&["42, 42"]).unwrap(), Some(42));
+
+    for values in [
+        &["42", "43"][..],
+        &["42, 7"],
+        &["-1"],
+        &["+42"],
+        &["4 2"],
+        &["x"],
+    ] {
+        let error = length(values).unwrap_err();
+        assert!(matches!(error, HttpError::InvalidHeader(_)), "{values:?}");
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{Listener, Pki, ok};
+use http_course_core::{ClientIdentity, HttpClient, HttpRequest, Origin};
+use p12_keystore::{Certificate, KeyStore, KeyStoreEntry, PrivateKeyChain};
+use rcgen::KeyPair;
+use url::Url;
+
+/// Starts a server that requires a client certificate signed by the CA of
+/// `pki`, and answers `200 OK` to every request.
+async fn serve(pki: &Pki) -> Url {
+    let listener = Listener::tls(pki.client_auth_server_config()).await;
+    listener.serve(|_| async { ok("ok") }).url
+}
+
+#[tokio::test]
+async fn presents_a_pem_identity() {
+    let pki = Pki::new();
+    let url = serve(&pki).await;
+    let client = HttpClient::new().tls(pki.tls_config().identity(pki.identity()));
+
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"ok");
+}
+
+#[tokio::test]
+async fn presents_a_pkcs12_identity() {
+    let pki = Pki::new();
+    let url = serve(&pki).await;
+    let chain = [
+        Certificate::from_der(pki.client_cert.der()).unwrap(),
+        Certificate::from_der(&pki.ca).unwrap(),
+    ];
+    let mut key_store = KeyStore::new();
+    key_store.add_entry(
+        "client",
+        KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(
+            pki.client_key.serialize_der(),
+            [1],
+            chain,
+        )),
+    );
+    let archive = key_store.writer("secret").write().unwrap();
+
+    let identity = ClientIdentity::from_pkcs12(&archive, "secret").unwrap();
+    let client = HttpClient::new().tls(pki.tls_config().identity(identity));
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+
+    assert!(ClientIdentity::from_pkcs12(&archive, "wrong").is_err());
+}
+
+#[tokio::test]
+async fn fails_without_an_identity() {
+    let pki = Pki::new();
+    let url = serve(&pki).await;
+    let client = HttpClient::new().tls(pki.tls_config());
+
+    assert!(client.send(HttpRequest::get(url)).await.is_err());
+}
+
+#[tokio::test]
+async fn presents_the_identity_of_the_origin() {
+    let pki = Pki::new();
+    let url = serve(&pki).await;
+    let origin = Origin::from_url(&url).unwrap();
+    let other = Origin::new("https", "localhost", 1);
+
+    let client = HttpClient::new().tls(pki.tls_config().identity_for(origin, pki.identity()));
+    let response = client.send(HttpRequest::get(url.clone())).await.unwrap();
+    assert_eq!(response.status, 200);
+
+    let client = HttpClient::new().tls(pki.tls_config().identity_for(other, pki.identity()));
+    assert!(client.send(HttpRequest::get(url)).await.is_err());
+}
+
+#[test]
+fn rejects_a_key_that_does_not_match_the_certificate() {
+    let pki = Pki::new();
+    let other_key = KeyPair::generate().unwrap();
+
+    let identity = ClientIdentity::from_pem(
+        pki.client_cert.pem().as_bytes(),
+        other_key.serialize_pem().as_bytes(),
+    );
+    assert!(identity.is_err());
+}
@@ -0,0 +1,29 @@
+//! The only test of its binary, as it changes the environment of the
+//! process.
+
+mod common;
+
+use common::{Listener, Pki, ok};
+use http_course_core::{HttpClient, HttpRequest, TlsConfig};
+
+#[tokio::test]
+async fn trusts_the_certificates_of_the_system() {
+    let pki = Pki::new();
+    let listener = Listener::tls(pki.server_config(vec![pki.server_cert.clone()])).await;
+    let url = listener.serve(|_| async { ok("ok") }).url;
+    let file = std::env::temp_dir().join(format!("http-course-{}-system.pem", std::process::id()));
+    std::fs::write(&file, &pki.ca_pem).unwrap();
+
+    // SAFETY: no other test of this binary runs, and the runtime of this
+    // one has a single thread
+    unsafe {
+        // read instead of the trust store of the system when set
+        std::env::set_var("SSL_CERT_FILE", &file);
+        std::env::remove_var("SSL_CERT_DIR");
+    }
+    let config = TlsConfig::new().webpki_roots(false).native_roots().unwrap();
+    let client = HttpClient::new().tls(config);
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    let _ = std::fs::remove_file(&file);
+}
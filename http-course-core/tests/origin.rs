@@ -0,0 +1,34 @@
+use http_course_core::Origin;
+use url::Url;
+
+#[test]
+fn writes_the_host_as_in_a_url() {
+    let origin = Origin::new("HTTPS", "Bücher.Example", 443);
+    assert_eq!(origin.scheme(), "https");
+    assert_eq!(origin.host(), "xn--bcher-kva.example");
+    assert_eq!(origin.to_string(), "https://xn--bcher-kva.example:443");
+
+    assert_eq!(Origin::new("http", "::1", 8080).host(), "[::1]");
+    assert_eq!(Origin::new("http", "[::1]", 8080).host(), "[::1]");
+    assert_eq!(Origin::new("http", "127.0.0.1", 8443).host(), "127.0.0.1");
+}
+
+#[test]
+fn takes_the_origin_of_a_url() {
+    let origin = Origin::from_url(&Url::parse("http://[::1]:8080/path").unwrap()).unwrap();
+    assert_eq!(origin, Origin::new("http", "::1", 8080));
+
+    // the default port of the scheme when the url has none
+    let origin = Origin::from_url(&Url::parse("https://bücher.example/").unwrap()).unwrap();
+    assert_eq!(origin, Origin::new("https", "xn--bcher-kva.example", 443));
+
+    let origin = Origin::from_url(&Url::parse("http+unix://%2Ftmp%2Fa.sock/").unwrap()).unwrap();
+    assert_eq!(origin.host(), "%2Ftmp%2Fa.sock");
+    assert_eq!(origin.port(), 0);
+}
+
+#[test]
+fn rejects_a_url_without_host() {
+    let url = Url::parse("data:text/plain,hello").unwrap();
+    assert!(Origin::from_url(&url).is_err());
+}
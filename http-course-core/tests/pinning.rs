@@ -0,0 +1,122 @@
+mod common;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use common::{Listener, Pki, ok};
+use http_course_core::{HttpClient, HttpError, HttpRequest, Origin, TlsConfig};
+use rcgen::{CertificateParams, KeyPair, PublicKeyData};
+use sha2::{Digest, Sha256};
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use url::Url;
+
+/// Starts a server that presents `chain` for the server key of `pki`, and
+/// answers `200 OK`.
+async fn serve(pki: &Pki, chain: Vec<CertificateDer<'static>>) -> Url {
+    let listener = Listener::tls(pki.server_config(chain)).await;
+    listener.serve(|_| async { ok("ok") }).url
+}
+
+/// The pin of `key`, as accepted by `TlsConfig::pin_sha256`.
+fn pin(key: &KeyPair) -> String {
+    format!(
+        "sha256/{}",
+        BASE64.encode(Sha256::digest(key.subject_public_key_info()))
+    )
+}
+
+async fn send(config: TlsConfig, url: Url) -> http_course_core::Result<u16> {
+    let client = HttpClient::new().tls(config);
+    Ok(client.send(HttpRequest::get(url)).await?.status)
+}
+
+#[tokio::test]
+async fn accepts_the_pinned_server_key() {
+    let pki = Pki::new();
+    let url = serve(&pki, vec![pki.server_cert.clone()]).await;
+    let origin = Origin::from_url(&url).unwrap();
+
+    let config = pki
+        .tls_config()
+        .pin_sha256(origin, &[&pin(&pki.server_key)])
+        .unwrap();
+    assert_eq!(send(config, url).await.unwrap(), 200);
+}
+
+#[tokio::test]
+async fn rejects_a_server_key_that_is_not_pinned() {
+    let pki = Pki::new();
+    let url = serve(&pki, vec![pki.server_cert.clone()]).await;
+    let origin = Origin::from_url(&url).unwrap();
+    let other = KeyPair::generate().unwrap();
+
+    let config = pki
+        .tls_config()
+        .pin_sha256(origin, &[&pin(&other)])
+        .unwrap();
+    let error = send(config, url).await.unwrap_err();
+    assert!(matches!(error, HttpError::PinMismatch(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn ignores_pinned_certificates_appended_to_the_chain() {
+    let pki = Pki::new();
+    // a certificate anybody can download, whose key the server does not hold
+    let pinned_key = KeyPair::generate().unwrap();
+    let pinned = CertificateParams::new(vec!["pinned.example".to_string()])
+        .unwrap()
+        .self_signed(&pinned_key)
+        .unwrap();
+    let url = serve(&pki, vec![pki.server_cert.clone(), pinned.der().clone()]).await;
+    let origin = Origin::from_url(&url).unwrap();
+
+    let config = pki
+        .tls_config()
+        .pin_sha256(origin, &[&pin(&pinned_key)])
+        .unwrap();
+    let error = send(config, url).await.unwrap_err();
+    assert!(matches!(error, HttpError::PinMismatch(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn pins_do_not_replace_the_certificate_verification() {
+    let pki = Pki::new();
+    let url = serve(&pki, vec![pki.server_cert.clone()]).await;
+    let origin = Origin::from_url(&url).unwrap();
+
+    // the key is pinned, but the CA is not trusted
+    let config = TlsConfig::new()
+        .webpki_roots(false)
+        .pin_sha256(origin, &[&pin(&pki.server_key)])
+        .unwrap();
+    let error = send(config, url).await.unwrap_err();
+    assert!(matches!(error, HttpError::Io(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn exposes_the_tls_session() {
+    let pki = Pki::new();
+    let url = serve(&pki, vec![pki.server_cert.clone(), pki.ca.clone()]).await;
+    let client = HttpClient::new().tls(pki.tls_config());
+
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    let info = response.tls_info.unwrap();
+    assert_eq!(info.protocol_version, "TLSv1.3");
+    assert!(info.cipher_suite.starts_with("TLS13_"));
+    assert_eq!(info.peer_certificates, [pki.server_cert.clone(), pki.ca]);
+    assert_eq!(
+        info.spki_sha256().map(|hash| format!("sha256/{hash}")),
+        Some(pin(&pki.server_key))
+    );
+}
+
+#[test]
+fn rejects_malformed_pins() {
+    let origin = Origin::new("https", "localhost", 443);
+    assert!(
+        TlsConfig::new()
+            .pin_sha256(origin.clone(), &["nope"])
+            .is_err()
+    );
+    // valid base64, but not 32 bytes
+    assert!(TlsConfig::new().pin_sha256(origin, &["AAAA"]).is_err());
+}
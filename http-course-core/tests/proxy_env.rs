@@ -0,0 +1,50 @@
+//! The only test of its binary, as it changes the environment of the
+//! process.
+
+mod common;
+
+use common::{ok, serve};
+use http_course_core::{HttpClient, HttpRequest, Proxy};
+use url::Url;
+
+/// Starts a server that answers every request with `name`.
+async fn serve_name(name: &'static str) -> u16 {
+    serve(move |_| async move { ok(name) }).await.port()
+}
+
+async fn get(proxy: Proxy, url: &Url) -> String {
+    let client = HttpClient::new().proxy(proxy);
+    let response = client.send(HttpRequest::get(url.clone())).await.unwrap();
+    String::from_utf8(response.body).unwrap()
+}
+
+#[tokio::test]
+async fn reads_the_proxies_from_the_environment() {
+    let origin = serve_name("origin").await;
+    let proxy = serve_name("proxy").await;
+    let url = Url::parse(&format!("http://127.0.0.1:{origin}/")).unwrap();
+    let proxy_url = format!("127.0.0.1:{proxy}");
+
+    // SAFETY: no other test of this binary runs, and the runtime of this
+    // one has a single thread
+    unsafe {
+        for name in [
+            "all_proxy",
+            "ALL_PROXY",
+            "http_proxy",
+            "NO_PROXY",
+            "no_proxy",
+        ] {
+            std::env::remove_var(name);
+        }
+        // what a CGI server sets from the `Proxy` header of a request
+        std::env::set_var("HTTP_PROXY", &proxy_url);
+    }
+    assert_eq!(get(Proxy::from_env().unwrap(), &url).await, "origin");
+
+    unsafe { std::env::set_var("http_proxy", &proxy_url) };
+    assert_eq!(get(Proxy::from_env().unwrap(), &url).await, "proxy");
+
+    unsafe { std::env::set_var("NO_PROXY", "localhost, 127.0.0.1") };
+    assert_eq!(get(Proxy::from_env().unwrap(), &url).await, "origin");
+}
@@ -0,0 +1,160 @@
+use http_course_core::{HttpConnection, HttpRequest};
+use tokio::io::AsyncReadExt;
+use url::Url;
+
+fn url(url: &str) -> Url {
+    Url::parse(url).unwrap()
+}
+
+/// Sends `request` over an in-memory stream and returns the bytes written.
+async fn sent(request: HttpRequest) -> String {
+    let (stream, mut server) = tokio::io::duplex(64 * 1024);
+    let (_, writer) = tokio::io::split(stream);
+    let response: &[u8] = b"HTTP/1.1 204 No Content\r\n\r\n";
+    let mut connection = HttpConnection::from_stream(tokio::io::join(response, writer));
+    connection.send(request).await.unwrap();
+    drop(connection);
+    let mut sent = String::new();
+    server.read_to_string(&mut sent).await.unwrap();
+    sent
+}
+
+type Constructor = fn(Url) -> HttpRequest;
+
+#[tokio::test]
+async fn writes_the_method_of_each_constructor() {
+    let constructors: [(Constructor, &str); 7] = [
+        (HttpRequest::get, "GET"),
+        (HttpRequest::head, "HEAD"),
+        (HttpRequest::post, "POST"),
+        (HttpRequest::put, "PUT"),
+        (HttpRequest::patch, "PATCH"),
+        (HttpRequest::delete, "DELETE"),
+        (HttpRequest::options, "OPTIONS"),
+    ];
+    for (constructor, method) in constructors {
+        let request = constructor(url("http://example.com:8080/items/1?full=yes"));
+        assert_eq!(
+            sent(request).await,
+            format!(
+                "{method} /items/1?full=yes HTTP/1.1\r\n\
+                 host: example.com:8080\r\n\
+                 accept-encoding: gzip\r\n\r\n"
+            )
+        );
+    }
+}
+
+#[tokio::test]
+async fn appends_encoded_query_pairs() {
+    let request = HttpRequest::get(url("http://example.com/search?lang=en"))
+        .query("q", "rust & http")
+        .query("page", "2");
+    let sent = sent(request).await;
+    assert!(
+        sent.starts_with("GET /search?lang=en&q=rust+%26+http&page=2 HTTP/1.1\r\n"),
+        "{sent}"
+    );
+}
+
+#[tokio::test]
+async fn writes_a_form_body() {
+    let request = HttpRequest::post(url("http://example.com/login"))
+        .form(&[("user", "pippo"), ("pw", "a&b=c")]);
+    assert_eq!(
+        sent(request).await,
+        "POST /login HTTP/1.1\r\n\
+         host: example.com\r\n\
+         accept-encoding: gzip\r\n\
+         content-type: application/x-www-form-urlencoded\r\n\
+         content-length: 23\r\n\r\n\
+         user=pippo&pw=a%26b%3Dc"
+    );
+}
+
+#[tokio::test]
+async fn writes_a_text_body() {
+    let request = HttpRequest::put(url("http://example.com/notes/1")).text("caffè");
+    assert_eq!(
+        sent(request).await,
+        "PUT /notes/1 HTTP/1.1\r\n\
+         host: example.com\r\n\
+         accept-encoding: gzip\r\n\
+         content-type: text/plain; charset=utf-8\r\n\
+         content-length: 6\r\n\r\n\
+         caffè"
+    );
+}
+
+#[tokio::test]
+async fn sets_the_content_length_of_the_last_body() {
+    let request = HttpRequest::post(url("http://example.com/"))
+        .body("a longer first body")
+        .body(vec![0; 3]);
+    let sent = sent(request).await;
+    assert!(sent.contains("\r\ncontent-length: 3\r\n"), "{sent}");
+    assert!(!sent.contains("content-length: 19"), "{sent}");
+    assert!(sent.ends_with("\r\n\r\n\0\0\0"), "{sent}");
+}
+
+#[tokio::test]
+async fn replaces_or_appends_headers() {
+    let request = HttpRequest::get(url("http://example.com/"))
+        .header("Accept", "text/html")
+        .header("accept", "application/json")
+        .append_header("X-Tag", "a")
+        .append_header("x-tag", "b");
+    let sent = sent(request).await;
+    assert!(sent.contains("\r\naccept: application/json\r\n"), "{sent}");
+    assert!(!sent.contains("text/html"), "{sent}");
+    assert!(sent.contains("\r\nX-Tag: a\r\nx-tag: b\r\n"), "{sent}");
+}
+
+#[tokio::test]
+async fn keeps_line_breaks_out_of_headers() {
+    let request = HttpRequest::get(url("http://example.com/"))
+        .header("X-A", "1\r\nX-Injected: yes")
+        .append_header("X-B\r\nX-Injected", "2\n")
+        .header("X-C", "3\0");
+    let sent = sent(request).await;
+    let lines: Vec<&str> = sent.split("\r\n").collect();
+    assert_eq!(
+        lines,
+        [
+            "GET / HTTP/1.1",
+            "host: example.com",
+            "accept-encoding: gzip",
+            "X-A: 1  X-Injected: yes",
+            "X-BX-Injected: 2 ",
+            "X-C: 3 ",
+            "",
+            ""
+        ]
+    );
+}
+
+#[tokio::test]
+async fn writes_the_host_of_ip_addresses_with_their_port() {
+    let hosts = [
+        ("http://[::1]:8080/", "[::1]:8080"),
+        ("https://127.0.0.1:8443/", "127.0.0.1:8443"),
+        ("https://127.0.0.1:443/", "127.0.0.1"),
+        ("http://[::1]/", "[::1]"),
+    ];
+    for (target, host) in hosts {
+        let request = HttpRequest::get(url(target));
+        assert_eq!(request.host(), host);
+        let sent = sent(request).await;
+        assert!(sent.contains(&format!("\r\nhost: {host}\r\n")), "{sent}");
+    }
+}
+
+#[tokio::test]
+async fn writes_an_international_host_in_punycode() {
+    let request = HttpRequest::get(url("http://Bücher.example:8080/"));
+    let sent = sent(request).await;
+    assert!(
+        sent.contains("\r\nhost: xn--bcher-kva.example:8080\r\n"),
+        "{sent}"
+    );
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{Listener, Received, ok, self_signed, serve};
+use http_course_core::{
+    CachingResolver, HttpClient, HttpRequest, Resolver, Resolving, StaticResolver, TlsConfig,
+};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use url::Url;
+
+const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Answers with the `Host` header of the request as the body.
+async fn answer_host(received: Received) -> Option<String> {
+    ok(received.header("host").unwrap_or_default())
+}
+
+/// Starts a plain HTTP server on loopback that answers every request with
+/// its `Host` header, and returns its port.
+async fn serve_host() -> u16 {
+    serve(answer_host).await.port()
+}
+
+/// Counts the lookups that reach it, and resolves everything to loopback.
+#[derive(Debug, Default)]
+struct CountingResolver {
+    lookups: Arc<AtomicUsize>,
+}
+
+impl Resolver for CountingResolver {
+    fn resolve<'a>(&'a self, _host: &'a str, port: u16) -> Resolving<'a> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move { Ok(vec![SocketAddr::new(LOCALHOST, port)]) })
+    }
+}
+
+#[tokio::test]
+async fn routes_a_host_to_a_static_address() {
+    let port = serve_host().await;
+    let resolver = StaticResolver::new().insert("gioyingtec.com", LOCALHOST);
+    let client = HttpClient::new().resolver(resolver);
+
+    let url = Url::parse(&format!("http://gioyingtec.com:{port}/")).unwrap();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, format!("gioyingtec.com:{port}").as_bytes());
+}
+
+#[tokio::test]
+async fn verifies_tls_under_the_real_name() {
+    let (cert, config) = self_signed(&["gioyingtec.com"]);
+    let port = Listener::tls(config).await.serve(answer_host).port();
+
+    let client = HttpClient::new()
+        .resolver(StaticResolver::new().insert("gioyingtec.com", LOCALHOST))
+        .tls(
+            TlsConfig::new()
+                .webpki_roots(false)
+                .add_ca_certificate(cert),
+        );
+    let url = Url::parse(&format!("https://gioyingtec.com:{port}/")).unwrap();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert!(response.tls_info.is_some());
+}
+
+#[tokio::test]
+async fn falls_back_for_the_other_hosts() {
+    let counting = CountingResolver::default();
+    let lookups = counting.lookups.clone();
+    let resolver = StaticResolver::new()
+        .insert("gioyingtec.com", LOCALHOST)
+        .fallback(counting);
+
+    let addresses = resolver.resolve("GIOYINGTEC.com", 443).await.unwrap();
+    assert_eq!(addresses, [SocketAddr::new(LOCALHOST, 443)]);
+    assert_eq!(lookups.load(Ordering::SeqCst), 0);
+
+    resolver.resolve("example.com", 443).await.unwrap();
+    assert_eq!(lookups.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn caches_the_addresses_for_the_ttl() {
+    let counting = CountingResolver::default();
+    let lookups = counting.lookups.clone();
+    let resolver = CachingResolver::new(counting, Duration::from_millis(100));
+
+    resolver.resolve("gioyingtec.com", 80).await.unwrap();
+    resolver.resolve("gioyingtec.com", 80).await.unwrap();
+    assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+    // another port is another entry
+    resolver.resolve("gioyingtec.com", 443).await.unwrap();
+    assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    resolver.resolve("gioyingtec.com", 80).await.unwrap();
+    assert_eq!(lookups.load(Ordering::SeqCst), 3);
+
+    resolver.clear();
+    resolver.resolve("gioyingtec.com", 80).await.unwrap();
+    assert_eq!(lookups.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn connects_to_ip_addresses_without_resolving() {
+    let port = serve_host().await;
+    let counting = CountingResolver::default();
+    let lookups = counting.lookups.clone();
+    let client = HttpClient::new().resolver(counting);
+
+    let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.body, format!("127.0.0.1:{port}").as_bytes());
+    assert_eq!(lookups.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use common::{Listener, Server, response, self_signed, serve};
+use http_course_core::{HttpClient, HttpError, HttpRequest, RetryPolicy};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Starts a server that answers the request number `n`, counted from 0,
+/// with `respond(n)`, or closes the connection on `None`.
+async fn serve_numbered(respond: fn(usize) -> Option<String>) -> Server {
+    serve(move |received| async move { respond(received.sequence) }).await
+}
+
+/// An empty response with `extra` header lines.
+fn empty(status: &str, extra: &str) -> Option<String> {
+    Some(response(status, extra, ""))
+}
+
+/// A policy with short backoffs, so that the tests do not wait.
+fn fast_policy() -> RetryPolicy {
+    RetryPolicy::new().initial_backoff(Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn waits_for_retry_after_in_seconds() {
+    let server = serve_numbered(|n| match n {
+        0 => empty("503 Service Unavailable", "Retry-After: 1\r\n"),
+        _ => empty("200 OK", ""),
+    })
+    .await;
+    let url = server.url("/");
+    let client = HttpClient::new().retry_policy(fast_policy());
+
+    let started = Instant::now();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests(), 2);
+}
+
+#[tokio::test]
+async fn waits_for_retry_after_as_a_date() {
+    let server = serve_numbered(|n| match n {
+        0 => {
+            let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(2));
+            empty("429 Too Many Requests", &format!("Retry-After: {date}\r\n"))
+        }
+        _ => empty("200 OK", ""),
+    })
+    .await;
+    let url = server.url("/");
+    let client = HttpClient::new().retry_policy(fast_policy());
+
+    let started = Instant::now();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    // the date is rounded down to the second
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests(), 2);
+}
+
+#[tokio::test]
+async fn gives_up_when_retry_after_is_past_the_max_elapsed_time() {
+    let server = serve_numbered(|_| empty("503 Service Unavailable", "Retry-After: 60\r\n")).await;
+    let url = server.url("/");
+    let client = HttpClient::new().retry_policy(fast_policy().max_elapsed(Duration::from_secs(5)));
+
+    let started = Instant::now();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 503);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(server.requests(), 1);
+}
+
+#[tokio::test]
+async fn caps_the_backoff() {
+    let server = serve_numbered(|_| empty("502 Bad Gateway", "")).await;
+    let url = server.url("/");
+    let policy = RetryPolicy::new()
+        .max_attempts(4)
+        .initial_backoff(Duration::from_secs(10))
+        .max_backoff(Duration::from_millis(50));
+    let client = HttpClient::new().retry_policy(policy);
+
+    let started = Instant::now();
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 502);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(server.requests(), 4);
+}
+
+#[tokio::test]
+async fn sends_non_idempotent_methods_once() {
+    let server = serve_numbered(|_| empty("503 Service Unavailable", "")).await;
+    let url = server.url("/");
+    let client = HttpClient::new().retry_policy(fast_policy());
+
+    let response = client
+        .send(HttpRequest::post(url.clone()).text("order"))
+        .await
+        .unwrap();
+    assert_eq!(response.status, 503);
+    assert_eq!(server.requests(), 1);
+
+    // DELETE is idempotent, so it gets the three attempts
+    let response = client.send(HttpRequest::delete(url)).await.unwrap();
+    assert_eq!(response.status, 503);
+    assert_eq!(server.requests(), 4);
+}
+
+#[tokio::test]
+async fn retries_a_connection_closed_by_the_server() {
+    let server = serve_numbered(|n| match n {
+        0 => None,
+        _ => empty("200 OK", ""),
+    })
+    .await;
+    let url = server.url("/");
+    let client = HttpClient::new().retry_policy(fast_policy());
+
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(server.requests(), 2);
+}
+
+#[tokio::test]
+async fn retries_a_refused_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    drop(listener);
+    let client = HttpClient::new().retry_policy(fast_policy());
+
+    let started = Instant::now();
+    let error = client.send(HttpRequest::get(url)).await.unwrap_err();
+    assert!(
+        matches!(&error, HttpError::Io(error) if error.kind() == ErrorKind::ConnectionRefused),
+        "{error:?}"
+    );
+    // two backoffs of at least 5 and 10 ms
+    assert!(started.elapsed() >= Duration::from_millis(15));
+}
+
+#[tokio::test]
+async fn does_not_retry_an_untrusted_certificate() {
+    let (_, config) = self_signed(&["localhost"]);
+    let server = Listener::tls(config).await.serve(|_| async { None });
+    let client = HttpClient::new().retry_policy(fast_policy());
+
+    let url = server.url("/");
+    let error = client.send(HttpRequest::get(url)).await.unwrap_err();
+    assert!(matches!(error, HttpError::Io(_)), "{error:?}");
+    assert_eq!(server.connections(), 1);
+}
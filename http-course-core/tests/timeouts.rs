@@ -0,0 +1,122 @@
+mod common;
+
+use common::{Connection, Listener, Received, Server, ok, serve};
+use http_course_core::{HttpClient, HttpError, HttpRequest, TimeoutKind};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use url::Url;
+
+async fn answer(_: Received) -> Option<String> {
+    ok("ok")
+}
+
+async fn never_answer(_: Received) -> Option<String> {
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    None
+}
+
+/// Sends the body one byte every 50 ms.
+async fn trickle(_: Received, mut connection: Connection) -> Option<Connection> {
+    let body = b"0123456789";
+    let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+    connection.write_all(head.as_bytes()).await.ok()?;
+    for byte in body {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        connection.write_all(&[*byte]).await.ok()?;
+    }
+    Some(connection)
+}
+
+async fn serve_trickle() -> Server {
+    Listener::tcp().await.serve_with(trickle)
+}
+
+fn timeout_kind(error: HttpError) -> TimeoutKind {
+    match error {
+        HttpError::Timeout(kind) => kind,
+        error => panic!("expected a timeout, got {error:?}"),
+    }
+}
+
+#[tokio::test]
+async fn reuses_idle_connections_with_a_read_timeout() {
+    let server = serve(answer).await;
+    let url = server.url("/");
+    let client = HttpClient::new().read_timeout(Duration::from_millis(200));
+
+    let response = client.send(HttpRequest::get(url.clone())).await.unwrap();
+    assert_eq!(response.body, b"ok");
+    // idle for longer than the read timeout
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.body, b"ok");
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn fails_a_read_that_waits_too_long() {
+    let url = serve(never_answer).await.url("/");
+    let client = HttpClient::new().read_timeout(Duration::from_millis(200));
+
+    let started = Instant::now();
+    let error = client.send(HttpRequest::get(url)).await.unwrap_err();
+    assert_eq!(timeout_kind(error), TimeoutKind::Read);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn restarts_the_read_timeout_when_data_arrives() {
+    let url = serve_trickle().await.url("/");
+    // the body takes 500 ms, but no read waits for more than 50 ms
+    let client = HttpClient::new().read_timeout(Duration::from_millis(200));
+
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.body, b"0123456789");
+}
+
+#[tokio::test]
+async fn fails_a_request_that_takes_too_long() {
+    let url = serve_trickle().await.url("/");
+    let client = HttpClient::new()
+        .read_timeout(Duration::from_millis(200))
+        .timeout(Duration::from_millis(250));
+
+    let error = client.send(HttpRequest::get(url)).await.unwrap_err();
+    assert_eq!(timeout_kind(error), TimeoutKind::Request);
+}
+
+#[tokio::test]
+async fn fails_a_tls_handshake_that_takes_too_long() {
+    // accepts the connection, but never answers the client hello
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut streams = Vec::new();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            streams.push(stream);
+        }
+    });
+    let client = HttpClient::new().tls_handshake_timeout(Duration::from_millis(200));
+
+    let url = Url::parse(&format!("https://localhost:{port}/")).unwrap();
+    let error = client.send(HttpRequest::get(url)).await.unwrap_err();
+    assert_eq!(timeout_kind(error), TimeoutKind::TlsHandshake);
+}
+
+#[tokio::test]
+async fn fails_a_connection_that_takes_too_long() {
+    // nobody accepts and the accept queue is full, so connecting hangs
+    let socket = TcpSocket::new_v4().unwrap();
+    socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let address: SocketAddr = socket.local_addr().unwrap();
+    let _listener = socket.listen(0).unwrap();
+    let _filler = TcpStream::connect(address).await.unwrap();
+    let client = HttpClient::new().connect_timeout(Duration::from_millis(200));
+
+    let url = Url::parse(&format!("http://{address}/")).unwrap();
+    let error = client.send(HttpRequest::get(url)).await.unwrap_err();
+    assert_eq!(timeout_kind(error), TimeoutKind::Connect);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::{Listener, Pki, ok, self_signed};
+use http_course_core::{HttpClient, HttpError, HttpRequest, TlsConfig};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use url::Url;
+
+/// A path in the temporary directory, unique to the test `name`.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("http-course-{}-{name}", std::process::id()))
+}
+
+/// Records the SNI names of the handshakes, `None` when the client sent
+/// none, and presents the certificate of `inner`.
+#[derive(Debug)]
+struct RecordSni {
+    inner: Arc<dyn ResolvesServerCert>,
+    names: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+impl ResolvesServerCert for RecordSni {
+    fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let name = hello.server_name().map(String::from);
+        self.names.lock().unwrap().push(name);
+        self.inner.resolve(hello)
+    }
+}
+
+/// Starts a server that presents the server certificate of `pki`.
+async fn serve(pki: &Pki) -> Url {
+    let listener = Listener::tls(pki.server_config(vec![pki.server_cert.clone()])).await;
+    listener.serve(|_| async { ok("ok") }).url
+}
+
+async fn send(config: TlsConfig, url: Url) -> http_course_core::Result<u16> {
+    let client = HttpClient::new().tls(config);
+    Ok(client.send(HttpRequest::get(url)).await?.status)
+}
+
+#[tokio::test]
+async fn trusts_the_cas_of_a_pem_file() {
+    let pki = Pki::new();
+    let url = serve(&pki).await;
+    let file = temp_path("ca.pem");
+    std::fs::write(&file, &pki.ca_pem).unwrap();
+
+    let config = TlsConfig::new()
+        .webpki_roots(false)
+        .add_ca_file(&file)
+        .unwrap();
+    assert_eq!(send(config, url).await.unwrap(), 200);
+    let _ = std::fs::remove_file(&file);
+}
+
+#[tokio::test]
+async fn trusts_the_cas_of_a_directory() {
+    let pki = Pki::new();
+    let url = serve(&pki).await;
+    let dir = temp_path("certs");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(dir.join("ca.pem"), &pki.ca_pem).unwrap();
+    // skipped, like the other files of `/etc/ssl/certs`
+    std::fs::write(dir.join("README"), "not a certificate").unwrap();
+    std::fs::create_dir(dir.join("nested")).unwrap();
+
+    let config = TlsConfig::new()
+        .webpki_roots(false)
+        .add_ca_dir(&dir)
+        .unwrap();
+    assert_eq!(send(config, url).await.unwrap(), 200);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rejects_a_file_without_certificates() {
+    let file = temp_path("empty.pem");
+    std::fs::write(&file, "").unwrap();
+
+    let error = TlsConfig::new().add_ca_file(&file).unwrap_err();
+    assert!(matches!(error, HttpError::Certificate(_)), "{error:?}");
+    let _ = std::fs::remove_file(&file);
+}
+
+#[test]
+fn rejects_a_directory_without_certificates() {
+    let dir = temp_path("no-certs");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+
+    let error = TlsConfig::new().add_ca_dir(&dir).unwrap_err();
+    assert!(matches!(error, HttpError::Certificate(_)), "{error:?}");
+
+    std::fs::write(dir.join("README"), "not a certificate").unwrap();
+    let error = TlsConfig::new().add_ca_dir(&dir).unwrap_err();
+    assert!(matches!(error, HttpError::Certificate(_)), "{error:?}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn accepts_an_untrusted_certificate_only_when_asked() {
+    // self-signed, and for another name
+    let (_, config) = self_signed(&["other.example"]);
+    let url = Listener::tls(config)
+        .await
+        .serve(|_| async { ok("ok") })
+        .url;
+
+    let error = send(TlsConfig::new(), url.clone()).await.unwrap_err();
+    assert!(matches!(error, HttpError::Io(_)), "{error:?}");
+
+    let config = TlsConfig::new().danger_accept_invalid_certs(true);
+    assert_eq!(send(config, url).await.unwrap(), 200);
+}
+
+#[tokio::test]
+async fn verifies_an_ip_address_against_the_ip_sans_without_sni() {
+    let (cert, mut config) = self_signed(&["127.0.0.1"]);
+    let names = Arc::new(Mutex::new(Vec::new()));
+    config.cert_resolver = Arc::new(RecordSni {
+        inner: config.cert_resolver.clone(),
+        names: names.clone(),
+    });
+    let server = Listener::tls(config).await.serve(|_| async { ok("ok") });
+    let trusted = TlsConfig::new()
+        .webpki_roots(false)
+        .add_ca_certificate(cert);
+
+    let url = Url::parse(&format!("https://127.0.0.1:{}/", server.port())).unwrap();
+    assert_eq!(send(trusted.clone(), url).await.unwrap(), 200);
+    assert_eq!(*names.lock().unwrap(), [None]);
+
+    // the certificate has no DNS name
+    let error = send(trusted, server.url("/")).await.unwrap_err();
+    assert!(matches!(error, HttpError::Io(_)), "{error:?}");
+}
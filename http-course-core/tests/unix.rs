@@ -0,0 +1,89 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{Listener, Received, Server, response, serve};
+use http_course_core::{HttpClient, HttpRequest};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The requests a server received.
+type Requests = Arc<Mutex<Vec<Received>>>;
+
+/// A socket path in the temporary directory, unique to the test `name`.
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("http-course-{}-{name}.sock", std::process::id()))
+}
+
+/// Starts a server on the Unix socket at `socket` that answers the request
+/// line with `respond`, and records the requests it receives.
+fn serve_unix(socket: &Path, respond: fn(&str) -> String) -> (Server, Requests) {
+    let requests = Requests::default();
+    let recorded = requests.clone();
+    let server = Listener::unix(socket).serve(move |received| {
+        let response = respond(&received.line);
+        recorded.lock().unwrap().push(received);
+        async move { Some(response) }
+    });
+    (server, requests)
+}
+
+#[tokio::test]
+async fn sends_the_path_and_localhost_over_the_socket() {
+    let socket = socket_path("path");
+    let (server, requests) = serve_unix(&socket, |_| response("200 OK", "", "from the socket"));
+    let client = HttpClient::new();
+
+    let url = server.url("/v1/info?all=1");
+    let response = client.send(HttpRequest::get(url)).await.unwrap();
+    assert_eq!(response.body, b"from the socket");
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].line, "GET /v1/info?all=1 HTTP/1.1");
+    assert_eq!(requests[0].header("host"), Some("localhost"));
+    let _ = std::fs::remove_file(&socket);
+}
+
+#[tokio::test]
+async fn follows_a_redirect_on_the_same_socket() {
+    let socket = socket_path("redirect");
+    let (server, requests) = serve_unix(&socket, |line| {
+        if line.starts_with("GET /old ") {
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n"
+                .to_string()
+        } else {
+            response("200 OK", "", "new")
+        }
+    });
+    let client = HttpClient::new();
+
+    let response = client
+        .send(HttpRequest::get(server.url("/old")))
+        .await
+        .unwrap();
+    assert_eq!(response.body, b"new");
+    assert_eq!(response.redirects, [server.url("/new")]);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    let _ = std::fs::remove_file(&socket);
+}
+
+#[tokio::test]
+async fn refuses_a_redirect_from_http_to_a_socket() {
+    let socket = socket_path("refused");
+    let (socket_server, requests) = serve_unix(&socket, |_| response("200 OK", "", "secret"));
+    let location = socket_server.url("/secret");
+    let server = serve(move |_| {
+        let extra = format!("Location: {location}\r\n");
+        async move { Some(response("302 Found", &extra, "")) }
+    })
+    .await;
+    let client = HttpClient::new();
+
+    let response = client
+        .send(HttpRequest::get(server.url("/")))
+        .await
+        .unwrap();
+    assert_eq!(response.status, 302);
+    assert!(response.redirects.is_empty());
+    assert!(requests.lock().unwrap().is_empty());
+    let _ = std::fs::remove_file(&socket);
+}